@@ -0,0 +1,30 @@
+//! Shared types for cloning many `src`/`dest` pairs in one native call.
+
+use crate::outcome::CloneMethod;
+use napi_derive::napi;
+
+/// One `src`/`dest` pair to clone as part of a `clone_batch_sync`/
+/// `clone_batch_async` call.
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct CloneBatchPair {
+    pub src: String,
+    pub dest: String,
+}
+
+/// Outcome of cloning a single pair within a batch.
+///
+/// Unlike `clone_sync`, a failed pair does not raise a JS exception or abort
+/// the rest of the batch — it's reported here instead so the caller can
+/// inspect every result. Exactly one of `method`/`error` is set.
+#[napi(object)]
+pub struct CloneBatchResult {
+    pub src: String,
+    pub dest: String,
+    /// The strategy that performed the clone, or `None` if `error` is set.
+    pub method: Option<CloneMethod>,
+    /// The failure message, or `None` on success. Names the `src`/`dest`
+    /// pair it came from (see `CloneError::with_paths`), so it's still
+    /// actionable if logged independently of the `src`/`dest` fields above.
+    pub error: Option<String>,
+}
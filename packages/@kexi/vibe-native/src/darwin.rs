@@ -21,19 +21,44 @@
 //! - A04:2021 - Insecure Design (errno race condition fix)
 
 use crate::error::{CloneError, CloneResult};
+use crate::outcome::CloneMethod;
 use std::ffi::CString;
 use std::fs;
 use std::os::unix::fs::FileTypeExt;
+use std::os::unix::io::AsRawFd;
 use std::path::Path;
 
 extern "C" {
     fn clonefile(src: *const libc::c_char, dst: *const libc::c_char, flags: u32) -> libc::c_int;
+    // fcopyfile's `state` argument is an opaque copyfile_state_t; a null
+    // pointer tells the kernel not to track/report progress, which is fine
+    // for our one-shot fallback copy.
+    fn fcopyfile(
+        from: libc::c_int,
+        to: libc::c_int,
+        state: *mut libc::c_void,
+        flags: u32,
+    ) -> libc::c_int;
     // SECURITY: Use __error() to get errno pointer immediately after syscall
     // This prevents race conditions where errno could be modified by signal handlers
     // or other threads between the syscall and error capture.
     fn __error() -> *mut libc::c_int;
 }
 
+// copyfile.h flags: copy ACLs, stat info, extended attributes, and data.
+const COPYFILE_ACL: u32 = 1 << 0;
+const COPYFILE_STAT: u32 = 1 << 1;
+const COPYFILE_XATTR: u32 = 1 << 2;
+const COPYFILE_DATA: u32 = 1 << 3;
+const COPYFILE_ALL: u32 = COPYFILE_ACL | COPYFILE_STAT | COPYFILE_XATTR | COPYFILE_DATA;
+
+// sys/clonefile.h flags.
+/// Clone the symlink itself instead of following it and rejecting the source.
+const CLONE_NOFOLLOW: u32 = 0x0001;
+/// Clone is owned by the calling user/group instead of copying the source's
+/// owner/group/SUID/SGID bits.
+const CLONE_NOOWNERCOPY: u32 = 0x0002;
+
 /// Capture errno immediately using libc's __error() function.
 /// This is safer than std::io::Error::last_os_error() as it avoids
 /// potential race conditions in multi-threaded environments.
@@ -50,22 +75,29 @@ fn capture_errno() -> i32 {
 /// Validate that the source path points to a supported file type.
 /// Only regular files and directories are allowed for security reasons.
 ///
+/// `allow_symlink` is set when the caller passed `clone_follow_symlinks:
+/// false`, i.e. requested `CLONE_NOFOLLOW` semantics: `clonefile()` will
+/// clone the symlink itself rather than its target, so a symlink source is
+/// no longer a following hazard and can be let through.
+///
 /// # Security
-/// Rejects symlinks, device files, sockets, and FIFOs to prevent:
+/// Rejects device files, sockets, and FIFOs (and symlinks, unless
+/// `allow_symlink`) to prevent:
 /// - Symlink following attacks (CWE-59)
 /// - Access to device files (could leak system information)
 /// - Socket/FIFO manipulation
-fn validate_file_type(path: &Path) -> CloneResult<()> {
-    let metadata = fs::symlink_metadata(path).map_err(|e| {
-        CloneError::from_errno("stat", e.raw_os_error().unwrap_or(0))
-    })?;
+fn validate_file_type(path: &Path, allow_symlink: bool) -> CloneResult<()> {
+    let metadata = fs::symlink_metadata(path)
+        .map_err(|e| CloneError::from_errno("stat", e.raw_os_error().unwrap_or(0)))?;
     let file_type = metadata.file_type();
 
-    // SECURITY: Only allow regular files and directories
+    // SECURITY: Only allow regular files and directories (plus symlinks when
+    // the caller is cloning them as symlinks rather than following them).
     let is_regular_file = file_type.is_file();
     let is_directory = file_type.is_dir();
+    let is_allowed_symlink = allow_symlink && file_type.is_symlink();
 
-    if is_regular_file || is_directory {
+    if is_regular_file || is_directory || is_allowed_symlink {
         return Ok(());
     }
 
@@ -84,7 +116,9 @@ fn validate_file_type(path: &Path) -> CloneResult<()> {
         "unknown"
     };
 
-    Err(CloneError::UnsupportedFileType { file_type: type_name })
+    Err(CloneError::UnsupportedFileType {
+        file_type: type_name,
+    })
 }
 
 fn path_to_cstring(path: &Path) -> CloneResult<CString> {
@@ -96,27 +130,115 @@ fn path_to_cstring(path: &Path) -> CloneResult<CString> {
     CString::new(path_str).map_err(|_| CloneError::NullByte)
 }
 
-/// Clone a file or directory using macOS clonefile()
+/// Clone a file or directory using macOS clonefile().
+///
+/// `clonefile()` natively recurses into directories, so there is no
+/// per-entry walk to police and `_skip_special_files` exists only to keep
+/// this function's signature aligned with the Linux implementation so
+/// `lib.rs` can call `platform::clone_file` without per-OS branches.
+///
+/// `clone_follow_symlinks` and `preserve_ownership` map directly to
+/// `clonefile()`'s `CLONE_NOFOLLOW`/`CLONE_NOOWNERCOPY` flags (inverted,
+/// since the flags are opt-out of the syscall's own defaults): passing
+/// `false` for either sets the corresponding flag. See `CloneOptions`.
+///
+/// When `allow_copy_fallback` is `true` and `clonefile()` fails with
+/// `ENOTSUP`/`EXDEV` on a *regular file* source, falls back to `fcopyfile()`
+/// (macOS's own fast copy primitive, also used by `cp(1)`). Directory
+/// sources have no such fallback today — `fcopyfile()` works on a single
+/// file descriptor, not a tree — so a `clonefile()` failure on a directory
+/// is always returned as-is.
+///
+/// `_preserve_times`/`_preserve_owner`/`_preserve_xattrs` are Linux-only
+/// `FICLONE` knobs (see `linux::clone_file`'s "Metadata preservation" note)
+/// with no effect here: `clonefile()`/`fcopyfile()` (via `COPYFILE_STAT`/
+/// `COPYFILE_XATTR`/`COPYFILE_ACL`) already carry over timestamps and
+/// extended attributes without them, and ownership too unless
+/// `preserve_ownership` is `false` (which sets `CLONE_NOOWNERCOPY` on the
+/// `clonefile()` call). They exist only to keep this
+/// function's signature aligned with the Linux implementation.
 ///
 /// # Security
-/// - Validates source file type before cloning (rejects symlinks, devices, etc.)
+/// - Validates source file type before cloning (rejects devices, sockets,
+///   FIFOs, and symlinks unless `clone_follow_symlinks` is `false`)
 /// - Captures errno immediately after syscall to prevent race conditions
-pub fn clone_file(src: &Path, dest: &Path) -> CloneResult<()> {
+#[allow(clippy::too_many_arguments)]
+pub fn clone_file(
+    src: &Path,
+    dest: &Path,
+    allow_copy_fallback: bool,
+    _skip_special_files: bool,
+    clone_follow_symlinks: bool,
+    preserve_ownership: bool,
+    _preserve_times: bool,
+    _preserve_owner: bool,
+    _preserve_xattrs: bool,
+) -> CloneResult<CloneMethod> {
     // SECURITY: Validate file type before cloning
-    // This prevents symlink attacks, device file access, and socket/FIFO exploitation
-    validate_file_type(src)?;
+    // This prevents device file access and socket/FIFO exploitation; symlinks
+    // are rejected too unless CLONE_NOFOLLOW was requested.
+    validate_file_type(src, !clone_follow_symlinks)?;
 
     let src_cstr = path_to_cstring(src)?;
     let dest_cstr = path_to_cstring(dest)?;
 
-    let result = unsafe { clonefile(src_cstr.as_ptr(), dest_cstr.as_ptr(), 0) };
+    let mut flags = 0u32;
+    if !clone_follow_symlinks {
+        flags |= CLONE_NOFOLLOW;
+    }
+    if !preserve_ownership {
+        flags |= CLONE_NOOWNERCOPY;
+    }
+
+    let result = unsafe { clonefile(src_cstr.as_ptr(), dest_cstr.as_ptr(), flags) };
 
     if result != 0 {
         // SECURITY: Capture errno immediately after syscall using __error()
         // This prevents TOCTOU race conditions where errno could be modified
         // by signal handlers or other threads between syscall and error capture
         let errno = capture_errno();
-        return Err(CloneError::from_errno("clonefile", errno));
+
+        let is_regular_file = fs::symlink_metadata(src)
+            .map(|m| m.file_type().is_file())
+            .unwrap_or(false);
+
+        if !allow_copy_fallback || !is_regular_file || !matches!(errno, libc::ENOTSUP | libc::EXDEV)
+        {
+            return Err(CloneError::from_errno("clonefile", errno));
+        }
+
+        fcopyfile_fallback(src, dest)?;
+        return Ok(CloneMethod::Fcopyfile);
+    }
+
+    Ok(CloneMethod::Clonefile)
+}
+
+/// Fall back to `fcopyfile()` (data + metadata + ACLs + xattrs) when
+/// `clonefile()` can't CoW-clone a regular file.
+fn fcopyfile_fallback(src: &Path, dest: &Path) -> CloneResult<()> {
+    let src_file = fs::File::open(src)
+        .map_err(|e| CloneError::from_errno("open source", e.raw_os_error().unwrap_or(0)))?;
+    let dest_file = fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(dest)
+        .map_err(|e| CloneError::from_errno("open dest", e.raw_os_error().unwrap_or(0)))?;
+
+    let result = unsafe {
+        fcopyfile(
+            src_file.as_raw_fd(),
+            dest_file.as_raw_fd(),
+            std::ptr::null_mut(),
+            COPYFILE_ALL,
+        )
+    };
+
+    if result != 0 {
+        let errno = capture_errno();
+        let _ = fs::remove_file(dest);
+        return Err(CloneError::from_errno("fcopyfile", errno));
     }
 
     Ok(())
@@ -169,16 +291,17 @@ mod tests {
         fs::write(&src, "test content").unwrap();
 
         // Clone
-        let result = clone_file(&src, &dest);
+        let result = clone_file(&src, &dest, false, false, true, true, false, false, false);
 
         // APFS is required for clonefile to work
         // On other filesystems, it may fail with ENOTSUP
         match result {
-            Ok(()) => {
+            Ok(CloneMethod::Clonefile) => {
                 // Success - file was cloned
                 let content = fs::read_to_string(&dest).unwrap();
                 assert_eq!(content, "test content");
             }
+            Ok(method) => panic!("Unexpected clone method: {:?}", method),
             Err(CloneError::SystemError { errno, .. }) if errno == libc::ENOTSUP => {
                 // Not supported on this filesystem - acceptable
             }
@@ -201,7 +324,7 @@ mod tests {
         symlink(&src, &link).unwrap();
 
         // Attempt to clone symlink should fail
-        let result = clone_file(&link, &dest);
+        let result = clone_file(&link, &dest, false, false, true, true, false, false, false);
 
         match result {
             Err(CloneError::UnsupportedFileType { file_type }) => {
@@ -211,6 +334,33 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_clone_symlink_with_nofollow() {
+        let temp_dir = TempDir::new().unwrap();
+        let src = temp_dir.path().join("src.txt");
+        let link = temp_dir.path().join("link.txt");
+        let dest = temp_dir.path().join("dest.txt");
+
+        fs::write(&src, "test content").unwrap();
+        symlink(&src, &link).unwrap();
+
+        // clone_follow_symlinks: false should pass CLONE_NOFOLLOW and clone
+        // the symlink itself instead of rejecting it.
+        let result = clone_file(&link, &dest, false, false, false, true, false, false, false);
+
+        match result {
+            Ok(CloneMethod::Clonefile) => {
+                let cloned_metadata = fs::symlink_metadata(&dest).unwrap();
+                assert!(cloned_metadata.file_type().is_symlink());
+            }
+            Ok(method) => panic!("Unexpected clone method: {:?}", method),
+            Err(CloneError::SystemError { errno, .. }) if errno == libc::ENOTSUP => {
+                // Not supported on this filesystem - acceptable
+            }
+            Err(e) => panic!("Unexpected error: {:?}", e),
+        }
+    }
+
     #[test]
     fn test_clone_directory() {
         let temp_dir = TempDir::new().unwrap();
@@ -221,11 +371,13 @@ mod tests {
         fs::create_dir(&src_dir).unwrap();
 
         // Clone directory
-        let result = clone_file(&src_dir, &dest_dir);
+        let result = clone_file(
+            &src_dir, &dest_dir, false, false, true, true, false, false, false,
+        );
 
         // APFS is required for clonefile to work
         match result {
-            Ok(()) => {
+            Ok(_method) => {
                 assert!(dest_dir.is_dir());
             }
             Err(CloneError::SystemError { errno, .. }) if errno == libc::ENOTSUP => {
@@ -241,7 +393,7 @@ mod tests {
         let src = temp_dir.path().join("src.txt");
         fs::write(&src, "test").unwrap();
 
-        assert!(validate_file_type(&src).is_ok());
+        assert!(validate_file_type(&src, false).is_ok());
     }
 
     #[test]
@@ -250,7 +402,7 @@ mod tests {
         let dir = temp_dir.path().join("dir");
         fs::create_dir(&dir).unwrap();
 
-        assert!(validate_file_type(&dir).is_ok());
+        assert!(validate_file_type(&dir, false).is_ok());
     }
 
     #[test]
@@ -262,10 +414,12 @@ mod tests {
         fs::write(&src, "test").unwrap();
         symlink(&src, &link).unwrap();
 
-        let result = validate_file_type(&link);
+        let result = validate_file_type(&link, false);
         assert!(matches!(
             result,
-            Err(CloneError::UnsupportedFileType { file_type: "symlink" })
+            Err(CloneError::UnsupportedFileType {
+                file_type: "symlink"
+            })
         ));
     }
 }
@@ -25,6 +25,23 @@ pub enum CloneError {
     // OWASP A01:2021 - Broken Access Control
     #[error("Unsupported file type: {file_type} (only regular files and directories are allowed)")]
     UnsupportedFileType { file_type: &'static str },
+
+    // SECURITY: Returned by `clone_in_sandbox_sync`/`clone_in_sandbox_async` when a
+    // (lexically normalized) path falls outside the caller-specified sandbox.
+    #[error("Path escapes sandbox: {path}")]
+    PathEscapesSandbox { path: String },
+
+    // Wraps another `CloneError` with the src/dest pair it was raised for, so
+    // a batch result's error message names the offending pair on its own
+    // instead of relying on the `CloneBatchResult.src`/`dest` fields it's
+    // attached to.
+    #[error("{source} (src: {src}, dest: {dest})")]
+    PathContext {
+        src: String,
+        dest: String,
+        #[source]
+        source: Box<CloneError>,
+    },
 }
 
 impl CloneError {
@@ -36,6 +53,17 @@ impl CloneError {
             errno,
         }
     }
+
+    /// Attach the `src`/`dest` pair this error was raised for, for callers
+    /// (e.g. `clone_batch_async`) that report errors independently of the
+    /// pair they came from.
+    pub fn with_paths(self, src: impl Into<String>, dest: impl Into<String>) -> Self {
+        Self::PathContext {
+            src: src.into(),
+            dest: dest.into(),
+            source: Box::new(self),
+        }
+    }
 }
 
 impl From<CloneError> for napi::Error {
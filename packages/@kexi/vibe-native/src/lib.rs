@@ -27,8 +27,8 @@
 //!
 //! ## Path Traversal Protection
 //!
-//! **Important**: This crate does NOT perform path traversal validation.
-//! The caller is responsible for validating that source and destination paths
+//! By default this crate does NOT perform path traversal validation: the
+//! caller is responsible for validating that source and destination paths
 //! are within allowed directories. Consider using:
 //!
 //! - `std::fs::canonicalize()` to resolve symlinks and ".." components
@@ -47,6 +47,12 @@
 //! }
 //! ```
 //!
+//! `canonicalize()` has a sharp edge though: it fails outright on a
+//! destination path that doesn't exist yet. `clone_in_sandbox_sync`/
+//! `clone_in_sandbox_async` offer an opt-in alternative that enforces
+//! sandboxing inside the crate itself, using a purely lexical `normalize_path`
+//! (see the `path` module) that never touches the filesystem.
+//!
 //! ## Errno Race Condition Fix (macOS)
 //!
 //! On macOS, errno is captured immediately after syscall using `__error()`
@@ -64,7 +70,12 @@
 #[cfg(not(any(target_os = "macos", target_os = "linux")))]
 compile_error!("This crate only supports macOS and Linux");
 
+mod batch;
 mod error;
+mod options;
+mod outcome;
+mod path;
+mod tree;
 
 #[cfg(target_os = "macos")]
 mod darwin;
@@ -72,9 +83,13 @@ mod darwin;
 #[cfg(target_os = "linux")]
 mod linux;
 
+use batch::{CloneBatchPair, CloneBatchResult};
 use napi::bindgen_prelude::*;
 use napi_derive::napi;
+use options::CloneOptions;
+use outcome::CloneOutcome;
 use std::path::Path;
+use tree::CloneTreeEntryResult;
 
 #[cfg(target_os = "macos")]
 use darwin as platform;
@@ -85,32 +100,491 @@ use linux as platform;
 /// Clone a file or directory synchronously using native Copy-on-Write
 ///
 /// - macOS: Uses clonefile() which supports both files and directories
-/// - Linux: Uses FICLONE ioctl which only supports files
+/// - Linux: Uses FICLONE ioctl. Directories are cloned by recursively
+///   FICLONE-ing their contents. When the destination filesystem doesn't
+///   support FICLONE, or the clone would cross filesystems, a fast
+///   `copy_file_range` (or buffered) copy is used instead unless
+///   `allow_copy_fallback` is explicitly set to `false`. `skip_special_files`
+///   (Linux only) skips symlinks/devices/sockets/FIFOs found while walking a
+///   directory source instead of aborting the clone; defaults to `false`.
+///
+/// `options` (macOS only) surfaces additional `clonefile()` flags; see
+/// `CloneOptions`.
+///
+/// `preserve_times`/`preserve_owner`/`preserve_xattrs` (Linux only; default
+/// `false`) additionally copy mtime/atime, ownership, and extended
+/// attributes from `src` to `dest` after a successful FICLONE — see
+/// `linux::clone_file`'s "Metadata preservation" note. No-ops on macOS,
+/// where `clonefile()`/`fcopyfile()` already preserve all of this.
+#[allow(clippy::too_many_arguments)]
 #[napi]
-pub fn clone_sync(src: String, dest: String) -> Result<()> {
-    platform::clone_file(Path::new(&src), Path::new(&dest)).map_err(|e| e.into())
+pub fn clone_sync(
+    src: String,
+    dest: String,
+    allow_copy_fallback: Option<bool>,
+    skip_special_files: Option<bool>,
+    options: Option<CloneOptions>,
+    preserve_times: Option<bool>,
+    preserve_owner: Option<bool>,
+    preserve_xattrs: Option<bool>,
+) -> Result<()> {
+    let options = options.unwrap_or(CloneOptions {
+        clone_follow_symlinks: None,
+        preserve_ownership: None,
+    });
+    platform::clone_file(
+        Path::new(&src),
+        Path::new(&dest),
+        allow_copy_fallback.unwrap_or(true),
+        skip_special_files.unwrap_or(false),
+        options.clone_follow_symlinks(),
+        options.preserve_ownership(),
+        preserve_times.unwrap_or(false),
+        preserve_owner.unwrap_or(false),
+        preserve_xattrs.unwrap_or(false),
+    )
+    .map(|_method| ())
+    .map_err(|e| e.into())
 }
 
 /// Clone a file or directory asynchronously using native Copy-on-Write
 ///
 /// - macOS: Uses clonefile() which supports both files and directories
-/// - Linux: Uses FICLONE ioctl which only supports files
+/// - Linux: Uses FICLONE ioctl. Directories are cloned by recursively
+///   FICLONE-ing their contents. When the destination filesystem doesn't
+///   support FICLONE, or the clone would cross filesystems, a fast
+///   `copy_file_range` (or buffered) copy is used instead unless
+///   `allow_copy_fallback` is explicitly set to `false`. `skip_special_files`
+///   (Linux only) skips symlinks/devices/sockets/FIFOs found while walking a
+///   directory source instead of aborting the clone; defaults to `false`.
+///
+/// `options` (macOS only) surfaces additional `clonefile()` flags; see
+/// `CloneOptions`.
+///
+/// `preserve_times`/`preserve_owner`/`preserve_xattrs` (Linux only; default
+/// `false`) additionally copy mtime/atime, ownership, and extended
+/// attributes from `src` to `dest` after a successful FICLONE — see
+/// `linux::clone_file`'s "Metadata preservation" note. No-ops on macOS,
+/// where `clonefile()`/`fcopyfile()` already preserve all of this.
+#[allow(clippy::too_many_arguments)]
 #[napi]
-pub async fn clone_async(src: String, dest: String) -> Result<()> {
+pub async fn clone_async(
+    src: String,
+    dest: String,
+    allow_copy_fallback: Option<bool>,
+    skip_special_files: Option<bool>,
+    options: Option<CloneOptions>,
+    preserve_times: Option<bool>,
+    preserve_owner: Option<bool>,
+    preserve_xattrs: Option<bool>,
+) -> Result<()> {
     // Run the blocking operation in a separate thread pool
+    let allow_copy_fallback = allow_copy_fallback.unwrap_or(true);
+    let skip_special_files = skip_special_files.unwrap_or(false);
+    let options = options.unwrap_or(CloneOptions {
+        clone_follow_symlinks: None,
+        preserve_ownership: None,
+    });
+    let clone_follow_symlinks = options.clone_follow_symlinks();
+    let preserve_ownership = options.preserve_ownership();
+    let preserve_times = preserve_times.unwrap_or(false);
+    let preserve_owner = preserve_owner.unwrap_or(false);
+    let preserve_xattrs = preserve_xattrs.unwrap_or(false);
     let result = tokio::task::spawn_blocking(move || {
-        platform::clone_file(Path::new(&src), Path::new(&dest))
+        platform::clone_file(
+            Path::new(&src),
+            Path::new(&dest),
+            allow_copy_fallback,
+            skip_special_files,
+            clone_follow_symlinks,
+            preserve_ownership,
+            preserve_times,
+            preserve_owner,
+            preserve_xattrs,
+        )
     })
     .await
     .map_err(|e| napi::Error::new(Status::GenericFailure, e.to_string()))?;
 
-    result.map_err(|e| e.into())
+    result.map(|_method| ()).map_err(|e| e.into())
+}
+
+/// Clone a file or directory synchronously, rejecting the clone if `src` or
+/// `dest` would (after lexical normalization) fall outside `sandbox_root`.
+///
+/// This is an opt-in alternative to `clone_sync` for callers who want this
+/// crate to enforce the sandboxing its docs otherwise leave entirely up to
+/// the caller. `sandbox_root` must already exist. See `path::ensure_in_sandbox`.
+#[allow(clippy::too_many_arguments)]
+#[napi]
+pub fn clone_in_sandbox_sync(
+    src: String,
+    dest: String,
+    sandbox_root: String,
+    allow_copy_fallback: Option<bool>,
+    skip_special_files: Option<bool>,
+    options: Option<CloneOptions>,
+    preserve_times: Option<bool>,
+    preserve_owner: Option<bool>,
+    preserve_xattrs: Option<bool>,
+) -> Result<()> {
+    path::ensure_in_sandbox(Path::new(&src), Path::new(&dest), Path::new(&sandbox_root))?;
+    clone_sync(
+        src,
+        dest,
+        allow_copy_fallback,
+        skip_special_files,
+        options,
+        preserve_times,
+        preserve_owner,
+        preserve_xattrs,
+    )
+}
+
+/// Asynchronous counterpart to `clone_in_sandbox_sync`.
+#[allow(clippy::too_many_arguments)]
+#[napi]
+pub async fn clone_in_sandbox_async(
+    src: String,
+    dest: String,
+    sandbox_root: String,
+    allow_copy_fallback: Option<bool>,
+    skip_special_files: Option<bool>,
+    options: Option<CloneOptions>,
+    preserve_times: Option<bool>,
+    preserve_owner: Option<bool>,
+    preserve_xattrs: Option<bool>,
+) -> Result<()> {
+    path::ensure_in_sandbox(Path::new(&src), Path::new(&dest), Path::new(&sandbox_root))?;
+    clone_async(
+        src,
+        dest,
+        allow_copy_fallback,
+        skip_special_files,
+        options,
+        preserve_times,
+        preserve_owner,
+        preserve_xattrs,
+    )
+    .await
+}
+
+/// Clone `pair.src` to `pair.dest`, reporting the outcome instead of
+/// returning a `Result` so a single failure can't abort a batch.
+#[allow(clippy::too_many_arguments)]
+fn clone_pair(
+    pair: &CloneBatchPair,
+    allow_copy_fallback: bool,
+    skip_special_files: bool,
+    options: CloneOptions,
+    preserve_times: bool,
+    preserve_owner: bool,
+    preserve_xattrs: bool,
+) -> CloneBatchResult {
+    let result = platform::clone_file(
+        Path::new(&pair.src),
+        Path::new(&pair.dest),
+        allow_copy_fallback,
+        skip_special_files,
+        options.clone_follow_symlinks(),
+        options.preserve_ownership(),
+        preserve_times,
+        preserve_owner,
+        preserve_xattrs,
+    );
+
+    match result {
+        Ok(method) => CloneBatchResult {
+            src: pair.src.clone(),
+            dest: pair.dest.clone(),
+            method: Some(method),
+            error: None,
+        },
+        Err(e) => CloneBatchResult {
+            src: pair.src.clone(),
+            dest: pair.dest.clone(),
+            method: None,
+            error: Some(
+                e.with_paths(pair.src.clone(), pair.dest.clone())
+                    .to_string(),
+            ),
+        },
+    }
+}
+
+/// Clone many `src`/`dest` pairs in one native call, e.g. to reflink a whole
+/// `node_modules` tree without paying a JS↔native round trip per file.
+///
+/// Returns one `CloneBatchResult` per pair, in order; a failed pair is
+/// reported in its result rather than aborting the rest of the batch.
+#[allow(clippy::too_many_arguments)]
+#[napi]
+pub fn clone_batch_sync(
+    pairs: Vec<CloneBatchPair>,
+    allow_copy_fallback: Option<bool>,
+    skip_special_files: Option<bool>,
+    options: Option<CloneOptions>,
+    preserve_times: Option<bool>,
+    preserve_owner: Option<bool>,
+    preserve_xattrs: Option<bool>,
+) -> Vec<CloneBatchResult> {
+    let allow_copy_fallback = allow_copy_fallback.unwrap_or(true);
+    let skip_special_files = skip_special_files.unwrap_or(false);
+    let options = options.unwrap_or(CloneOptions {
+        clone_follow_symlinks: None,
+        preserve_ownership: None,
+    });
+    let preserve_times = preserve_times.unwrap_or(false);
+    let preserve_owner = preserve_owner.unwrap_or(false);
+    let preserve_xattrs = preserve_xattrs.unwrap_or(false);
+
+    pairs
+        .iter()
+        .map(|pair| {
+            clone_pair(
+                pair,
+                allow_copy_fallback,
+                skip_special_files,
+                options,
+                preserve_times,
+                preserve_owner,
+                preserve_xattrs,
+            )
+        })
+        .collect()
+}
+
+/// Asynchronous counterpart to `clone_batch_sync`.
+///
+/// CoW clones are metadata-bound rather than CPU-bound, so pairs are
+/// dispatched across `spawn_blocking` with bounded concurrency (one
+/// in-flight clone per available CPU by default) instead of serializing them
+/// or spawning everything at once. Concurrency is a streaming pool, not a
+/// round of batches: a `tokio::sync::Semaphore` gates how many clones are
+/// in flight at once, so a permit freed by an early finisher is immediately
+/// picked up by the next pending pair instead of waiting for every pair in
+/// its round to finish first. `max_concurrency` overrides the default pool
+/// size, e.g. to cap how many file descriptors a very large batch can hold
+/// open at once; it's clamped to at least `1`.
+#[allow(clippy::too_many_arguments)]
+#[napi]
+pub async fn clone_batch_async(
+    pairs: Vec<CloneBatchPair>,
+    allow_copy_fallback: Option<bool>,
+    skip_special_files: Option<bool>,
+    options: Option<CloneOptions>,
+    preserve_times: Option<bool>,
+    preserve_owner: Option<bool>,
+    preserve_xattrs: Option<bool>,
+    max_concurrency: Option<u32>,
+) -> Result<Vec<CloneBatchResult>> {
+    let allow_copy_fallback = allow_copy_fallback.unwrap_or(true);
+    let skip_special_files = skip_special_files.unwrap_or(false);
+    let options = options.unwrap_or(CloneOptions {
+        clone_follow_symlinks: None,
+        preserve_ownership: None,
+    });
+    let preserve_times = preserve_times.unwrap_or(false);
+    let preserve_owner = preserve_owner.unwrap_or(false);
+    let preserve_xattrs = preserve_xattrs.unwrap_or(false);
+
+    let concurrency = match max_concurrency {
+        Some(n) => n.max(1) as usize,
+        None => std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1),
+    };
+
+    let semaphore = std::sync::Arc::new(tokio::sync::Semaphore::new(concurrency));
+    let mut handles = Vec::with_capacity(pairs.len());
+    for pair in pairs {
+        let permit = semaphore
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("semaphore is never closed");
+        handles.push(tokio::task::spawn_blocking(move || {
+            let result = clone_pair(
+                &pair,
+                allow_copy_fallback,
+                skip_special_files,
+                options,
+                preserve_times,
+                preserve_owner,
+                preserve_xattrs,
+            );
+            drop(permit);
+            result
+        }));
+    }
+
+    let mut results = Vec::with_capacity(handles.len());
+    for handle in handles {
+        let result = handle
+            .await
+            .map_err(|e| napi::Error::new(Status::GenericFailure, e.to_string()))?;
+        results.push(result);
+    }
+
+    Ok(results)
+}
+
+/// Clone a file or directory synchronously, reporting which underlying
+/// strategy actually performed the clone (`CloneMethod`) rather than just
+/// success/failure, so callers can log or surface CoW savings.
+///
+/// Always allows the copy fallback, since the point of this entry point is
+/// to report whichever strategy succeeded; use `clone_sync` with
+/// `allow_copy_fallback: Some(false)` for clone-or-error semantics.
+#[napi]
+pub fn clone_with_outcome(src: String, dest: String) -> Result<CloneOutcome> {
+    platform::clone_file(
+        Path::new(&src),
+        Path::new(&dest),
+        true,
+        false,
+        true,
+        true,
+        false,
+        false,
+        false,
+    )
+    .map(|method| CloneOutcome { method })
+    .map_err(|e| e.into())
+}
+
+/// Asynchronous counterpart to `clone_with_outcome`.
+#[napi]
+pub async fn clone_with_outcome_async(src: String, dest: String) -> Result<CloneOutcome> {
+    let result = tokio::task::spawn_blocking(move || {
+        platform::clone_file(
+            Path::new(&src),
+            Path::new(&dest),
+            true,
+            false,
+            true,
+            true,
+            false,
+            false,
+            false,
+        )
+    })
+    .await
+    .map_err(|e| napi::Error::new(Status::GenericFailure, e.to_string()))?;
+
+    result
+        .map(|method| CloneOutcome { method })
+        .map_err(|e| e.into())
+}
+
+/// Clone a file or directory, always allowing the copy fallback so a
+/// non-reflink destination filesystem (ext4, tmpfs, etc.) never hard-fails
+/// the clone, and reporting which strategy actually ran: a true
+/// copy-on-write clone (`Ficlone`/`Clonefile`), a `copy_file_range` (Linux)
+/// or `fcopyfile` (macOS) byte copy, or (on Linux, when the filesystem
+/// supports neither, e.g. `copy_file_range` on a FIFO dest) a plain
+/// buffered read/write copy (`Buffered`).
+///
+/// This is exactly `clone_with_outcome`, which always allows the copy
+/// fallback too, and the layered reflink → `copy_file_range` →
+/// buffered-copy chain lives once in `platform::clone_file` rather than
+/// being reimplemented here. This alias exists for callers who'd otherwise
+/// look for a dedicated "clone or fall back" entry point under this name.
+#[napi]
+pub fn clone_with_fallback(src: String, dest: String) -> Result<CloneOutcome> {
+    clone_with_outcome(src, dest)
+}
+
+/// Asynchronous counterpart to `clone_with_fallback`.
+#[napi]
+pub async fn clone_with_fallback_async(src: String, dest: String) -> Result<CloneOutcome> {
+    clone_with_outcome_async(src, dest).await
 }
 
 /// Clone a file or directory synchronously (alias for cloneSync for backward compatibility)
 #[napi]
 pub fn clone(src: String, dest: String) -> Result<()> {
-    clone_sync(src, dest)
+    clone_sync(src, dest, None, None, None, None, None, None)
+}
+
+/// Recursively clone a directory synchronously, walking `src` itself (rather
+/// than relying on `clone_sync`'s OS-native directory handling) so every
+/// visited path gets its own `CloneTreeEntryResult` instead of the whole
+/// call succeeding or failing atomically.
+///
+/// `recreate_symlinks` (defaults to `false`) recreates symlinks found in the
+/// walk as symlinks in `dest` instead of treating them like any other
+/// special file under `skip_special_files`. `stop_on_error` (defaults to
+/// `false`) chooses between the two failure modes: `true` aborts on the
+/// first failing path and removes the partially created `dest` tree; `false`
+/// continues past failures and reports each one in its own result.
+///
+/// `preserve_times`/`preserve_owner`/`preserve_xattrs` (Linux only; default
+/// `false`) are forwarded to `platform::clone_file` for each regular file in
+/// the walk — see `clone_sync`'s doc comment.
+#[allow(clippy::too_many_arguments)]
+#[napi]
+pub fn clone_tree_sync(
+    src: String,
+    dest: String,
+    allow_copy_fallback: Option<bool>,
+    skip_special_files: Option<bool>,
+    recreate_symlinks: Option<bool>,
+    stop_on_error: Option<bool>,
+    preserve_times: Option<bool>,
+    preserve_owner: Option<bool>,
+    preserve_xattrs: Option<bool>,
+) -> Vec<CloneTreeEntryResult> {
+    tree::clone_tree(
+        Path::new(&src),
+        Path::new(&dest),
+        allow_copy_fallback.unwrap_or(true),
+        skip_special_files.unwrap_or(false),
+        recreate_symlinks.unwrap_or(false),
+        stop_on_error.unwrap_or(false),
+        preserve_times.unwrap_or(false),
+        preserve_owner.unwrap_or(false),
+        preserve_xattrs.unwrap_or(false),
+    )
+}
+
+/// Asynchronous counterpart to `clone_tree_sync`.
+#[allow(clippy::too_many_arguments)]
+#[napi]
+pub async fn clone_tree_async(
+    src: String,
+    dest: String,
+    allow_copy_fallback: Option<bool>,
+    skip_special_files: Option<bool>,
+    recreate_symlinks: Option<bool>,
+    stop_on_error: Option<bool>,
+    preserve_times: Option<bool>,
+    preserve_owner: Option<bool>,
+    preserve_xattrs: Option<bool>,
+) -> Result<Vec<CloneTreeEntryResult>> {
+    let allow_copy_fallback = allow_copy_fallback.unwrap_or(true);
+    let skip_special_files = skip_special_files.unwrap_or(false);
+    let recreate_symlinks = recreate_symlinks.unwrap_or(false);
+    let stop_on_error = stop_on_error.unwrap_or(false);
+    let preserve_times = preserve_times.unwrap_or(false);
+    let preserve_owner = preserve_owner.unwrap_or(false);
+    let preserve_xattrs = preserve_xattrs.unwrap_or(false);
+
+    tokio::task::spawn_blocking(move || {
+        tree::clone_tree(
+            Path::new(&src),
+            Path::new(&dest),
+            allow_copy_fallback,
+            skip_special_files,
+            recreate_symlinks,
+            stop_on_error,
+            preserve_times,
+            preserve_owner,
+            preserve_xattrs,
+        )
+    })
+    .await
+    .map_err(|e| napi::Error::new(Status::GenericFailure, e.to_string()))
 }
 
 /// Check if native clone operations are available
@@ -120,8 +594,8 @@ pub fn is_available() -> bool {
 }
 
 /// Check if directory cloning is supported
-/// - macOS clonefile: true (supports directories)
-/// - Linux FICLONE: false (files only)
+/// - macOS clonefile: true (native directory support)
+/// - Linux FICLONE: true (recursive walk cloning each regular file)
 #[napi]
 pub fn supports_directory() -> bool {
     platform::supports_directory()
@@ -1,58 +1,388 @@
 //! Linux implementation using FICLONE ioctl
 //!
 //! FICLONE creates a copy-on-write clone on filesystems that support it
-//! (Btrfs, XFS with reflink support). Only works for regular files.
+//! (Btrfs, XFS with reflink support). It only clones a single regular file,
+//! so directories are cloned by recursively walking the source and
+//! `FICLONE`-ing each regular file underneath (see `clone_directory`).
+//!
+//! When the source/destination filesystem doesn't support FICLONE (or the
+//! clone would cross filesystems), `clone_file` can fall back to a fast
+//! in-kernel copy via `copy_file_range(2)`, and if that is unavailable too,
+//! a plain buffered read/write loop. See `clone_file`'s `allow_copy_fallback`
+//! parameter to opt out and require true copy-on-write semantics.
 //!
 //! # Security Considerations
 //!
 //! This module implements several security measures:
 //!
-//! - **File type validation**: Only regular files are allowed.
-//!   Symlinks, device files, sockets, FIFOs, and directories are rejected to prevent:
+//! - **File type validation**: Only regular files and directories are
+//!   allowed. Symlinks, device files, sockets, and FIFOs are rejected to
+//!   prevent:
 //!   - Symlink attacks (CWE-59, CWE-61)
 //!   - Device file access escalation
 //!   - Socket/FIFO exploitation
 //!
-//! Note: FICLONE only supports regular files, so directory cloning is not available.
+//!   During a directory walk, the same rule applies to every entry found;
+//!   `clone_file`'s `skip_special_files` flag chooses whether such an entry
+//!   aborts the clone or is silently skipped.
+//!
+//! - **TOCTOU-safe validation**: the source of a regular-file clone is
+//!   opened with `O_NOFOLLOW`, and the file type is read back from the
+//!   resulting descriptor with `fstat`, rather than stat-ing the path and
+//!   opening it as two separate steps. This closes the race (CWE-367) where
+//!   a path is swapped for a symlink between a `stat()` check and a later
+//!   `open()` of the same path. The destination path itself is never
+//!   opened: a symlink already at `dest` is rejected, and the clone is
+//!   written into a freshly created (`O_EXCL | O_NOFOLLOW`) sibling temp
+//!   file that is then renamed onto `dest` (see "Atomicity" below).
 //!
 //! # OWASP References
 //! - A01:2021 - Broken Access Control (file type validation)
+//!
+//! # Metadata preservation
+//!
+//! FICLONE only duplicates file data; `clone_regular_file_fd` carries over
+//! the permission bits itself. `OpenOptions::mode` is still subject to the
+//! process umask even on the freshly created temp file the clone lands in
+//! (see "Atomicity" below), so it alone can leave the temp file with
+//! narrower permissions than `src`; `clone_regular_file_fd` corrects this
+//! with an `fchmod` once it knows the mode differs (see
+//! `enforce_dest_mode`). `preserve_times`, `preserve_owner`, and
+//! `preserve_xattrs` are opt-in flags that, after a successful clone,
+//! additionally copy mtime/atime (`File::set_times`), ownership (`fchown`,
+//! skipped silently on `EPERM` when the process isn't privileged), and
+//! extended attributes (`flistxattr`/`fgetxattr`/`fsetxattr`) from source to
+//! destination, so backup/snapshot callers can get a destination that's
+//! indistinguishable from the source.
+//!
+//! # Atomicity
+//!
+//! `clone_regular_file_fd` never opens `dest` itself. It clones into a
+//! freshly created sibling temp file (same directory, so the final
+//! `rename(2)` stays on one filesystem) and renames that into place only
+//! once the clone and every opted-in metadata-preservation step has
+//! succeeded. Opening `dest` directly with `O_TRUNC` would destroy a
+//! pre-existing destination's contents before FICLONE even runs, so any
+//! failure partway through (e.g. `allow_copy_fallback: false` and FICLONE
+//! unsupported) would leave the caller with a truncated file instead of
+//! either a full clone or their original data untouched.
 
 use crate::error::{CloneError, CloneResult};
+use crate::outcome::CloneMethod;
+use std::ffi::CString;
 use std::fs::{self, File, OpenOptions};
-use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+use std::io;
+use std::mem::MaybeUninit;
+use std::os::unix::fs::{FileTypeExt, OpenOptionsExt, PermissionsExt};
 use std::os::unix::io::AsRawFd;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime};
 
 // FICLONE ioctl request code
 nix::ioctl_write_int!(ficlone, 0x94, 9);
 
-/// Validate that the source path points to a regular file.
-/// Only regular files are allowed for FICLONE.
-///
-/// # Security
-/// Rejects symlinks, device files, sockets, FIFOs, and directories to prevent:
-/// - Symlink following attacks (CWE-59)
-/// - Access to device files (could leak system information)
-/// - Socket/FIFO manipulation
-fn validate_file_type(path: &Path) -> CloneResult<()> {
-    let metadata = fs::symlink_metadata(path).map_err(|e| {
-        CloneError::from_errno("stat", e.raw_os_error().unwrap_or(0))
-    })?;
-    let file_type = metadata.file_type();
-
-    // SECURITY: Only allow regular files (FICLONE does not support directories)
-    let is_regular_file = file_type.is_file();
-
-    if is_regular_file {
+// The `libc` crate doesn't bind the xattr syscalls, so they're declared
+// directly here, the same way darwin.rs declares `clonefile`/`fcopyfile`.
+extern "C" {
+    fn flistxattr(fd: libc::c_int, list: *mut libc::c_char, size: libc::size_t) -> libc::ssize_t;
+    fn fgetxattr(
+        fd: libc::c_int,
+        name: *const libc::c_char,
+        value: *mut libc::c_void,
+        size: libc::size_t,
+    ) -> libc::ssize_t;
+    fn fsetxattr(
+        fd: libc::c_int,
+        name: *const libc::c_char,
+        value: *const libc::c_void,
+        size: libc::size_t,
+        flags: libc::c_int,
+    ) -> libc::c_int;
+}
+
+/// Errno values after which FICLONE is considered "not usable here" rather
+/// than a hard failure, so a fallback copy should be attempted instead.
+fn is_ficlone_fallback_errno(errno: i32) -> bool {
+    matches!(
+        errno,
+        libc::ENOTSUP | libc::EOPNOTSUPP | libc::EXDEV | libc::EINVAL
+    )
+}
+
+/// Copy `len` bytes from `src_file` to `dest_file` using `copy_file_range(2)`,
+/// looping until the whole source has been copied (the kernel may transfer
+/// less than requested per call). Returns `Err` with the raw `io::Error` on
+/// failure so the caller can decide whether to fall back further.
+fn copy_file_range_loop(src_file: &File, dest_file: &File, mut len: u64) -> io::Result<()> {
+    let src_fd = src_file.as_raw_fd();
+    let dest_fd = dest_file.as_raw_fd();
+
+    while len > 0 {
+        let chunk = len.min(isize::MAX as u64) as usize;
+        let copied = unsafe {
+            libc::copy_file_range(
+                src_fd,
+                std::ptr::null_mut(),
+                dest_fd,
+                std::ptr::null_mut(),
+                chunk,
+                0,
+            )
+        };
+
+        if copied < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        if copied == 0 {
+            // Source exhausted (or len was wrong); nothing more to copy.
+            break;
+        }
+        len -= copied as u64;
+    }
+
+    Ok(())
+}
+
+/// Copy the remainder of `src_file` to `dest_file` with a plain buffered
+/// read/write loop. Used when neither FICLONE nor `copy_file_range` works,
+/// e.g. across some FUSE filesystems.
+fn buffered_copy(mut src_file: &File, mut dest_file: &File) -> io::Result<()> {
+    io::copy(&mut src_file, &mut dest_file)?;
+    Ok(())
+}
+
+/// Open `path` for reading with `O_NOFOLLOW | O_CLOEXEC`, so a symlink at
+/// `path` is rejected by the open itself (`ELOOP`) instead of being
+/// followed. Call `fstat_file` on the result to find out what it actually
+/// is — see the module's TOCTOU note for why these are split from a single
+/// `stat`-then-`open`.
+fn open_nofollow(path: &Path) -> CloneResult<File> {
+    OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_NOFOLLOW | libc::O_CLOEXEC)
+        .open(path)
+        .map_err(|e| map_open_errno("open source", e.raw_os_error().unwrap_or(0)))
+}
+
+/// Map an errno from an `O_NOFOLLOW` open to a `CloneError`, surfacing
+/// `ELOOP` (the path was a symlink) as the same `UnsupportedFileType` error
+/// `fstat`-based validation would have produced.
+fn map_open_errno(operation: &'static str, errno: i32) -> CloneError {
+    if errno == libc::ELOOP {
+        CloneError::UnsupportedFileType {
+            file_type: "symlink",
+        }
+    } else {
+        CloneError::from_errno(operation, errno)
+    }
+}
+
+/// `fstat(2)` an open file descriptor.
+fn fstat_file(file: &File) -> io::Result<libc::stat> {
+    let mut stat = MaybeUninit::<libc::stat>::uninit();
+    let result = unsafe { libc::fstat(file.as_raw_fd(), stat.as_mut_ptr()) };
+    if result != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    // SAFETY: fstat() returned success, so `stat` was fully written.
+    Ok(unsafe { stat.assume_init() })
+}
+
+/// Build a `SystemTime` from a `stat` timespec, clamping a (practically
+/// impossible) pre-epoch timestamp to `UNIX_EPOCH` rather than panicking.
+fn system_time_from_timespec(secs: libc::time_t, nsecs: i64) -> SystemTime {
+    if secs >= 0 {
+        SystemTime::UNIX_EPOCH + Duration::new(secs as u64, nsecs as u32)
+    } else {
+        SystemTime::UNIX_EPOCH
+    }
+}
+
+/// Set `dest_file`'s mtime/atime to match `src_stat`, via `File::set_times`
+/// (which uses `futimens(2)` under the hood on Linux).
+fn preserve_times(dest_file: &File, src_stat: &libc::stat) -> CloneResult<()> {
+    let times = fs::FileTimes::new()
+        .set_accessed(system_time_from_timespec(
+            src_stat.st_atime,
+            src_stat.st_atime_nsec,
+        ))
+        .set_modified(system_time_from_timespec(
+            src_stat.st_mtime,
+            src_stat.st_mtime_nsec,
+        ));
+
+    dest_file
+        .set_times(times)
+        .map_err(|e| CloneError::from_errno("set dest times", e.raw_os_error().unwrap_or(0)))
+}
+
+/// Force `dest_file` to `src_mode`'s permission bits via `fchmod`, matching
+/// std's `open_and_set_permissions` hardening: `OpenOptions::mode` is still
+/// subject to umask even on a freshly created file, so relying on it alone
+/// can leave dest with narrower permissions than `src`. Also called a
+/// second time, after `preserve_owner`'s `fchown`, to restore
+/// `S_ISUID`/`S_ISGID` bits POSIX clears on a successful chown. Skipped when
+/// `dest_file` turns out not to be a regular file (e.g. `/dev/null`), since
+/// mutating a special file's mode is never what's wanted here, and when the
+/// mode already matches, to avoid a needless syscall on the common path.
+fn enforce_dest_mode(dest_file: &File, src_mode: u32) -> CloneResult<()> {
+    let dest_stat = fstat_file(dest_file)
+        .map_err(|e| CloneError::from_errno("fstat dest", e.raw_os_error().unwrap_or(0)))?;
+    if dest_stat.st_mode & libc::S_IFMT != libc::S_IFREG {
+        return Ok(());
+    }
+
+    let want_mode = src_mode & 0o7777;
+    if dest_stat.st_mode & 0o7777 == want_mode {
+        return Ok(());
+    }
+
+    let result = unsafe { libc::fchmod(dest_file.as_raw_fd(), want_mode as libc::mode_t) };
+    if result != 0 {
+        let errno = io::Error::last_os_error().raw_os_error().unwrap_or(0);
+        return Err(CloneError::from_errno("fchmod dest", errno));
+    }
+    Ok(())
+}
+
+/// Replicate `src_stat`'s owning user/group onto `dest_file` via `fchown`.
+/// An unprivileged process can't `chown` to an arbitrary owner, so `EPERM`
+/// is swallowed rather than failing the whole clone.
+fn preserve_owner(dest_file: &File, src_stat: &libc::stat) -> CloneResult<()> {
+    let result = unsafe { libc::fchown(dest_file.as_raw_fd(), src_stat.st_uid, src_stat.st_gid) };
+    if result == 0 {
+        return Ok(());
+    }
+
+    let errno = io::Error::last_os_error().raw_os_error().unwrap_or(0);
+    if errno == libc::EPERM {
         return Ok(());
     }
+    Err(CloneError::from_errno("fchown dest", errno))
+}
+
+/// Copy every extended attribute from `src_file` to `dest_file` by listing
+/// the source's xattr names and re-setting each one on the destination fd.
+/// A filesystem with no xattr support at all (`ENOTSUP`/`EOPNOTSUPP`) is not
+/// treated as a failure, since there's nothing to preserve.
+fn preserve_xattrs(src_file: &File, dest_file: &File) -> CloneResult<()> {
+    let src_fd = src_file.as_raw_fd();
+    let dest_fd = dest_file.as_raw_fd();
+
+    let list_len = unsafe { flistxattr(src_fd, std::ptr::null_mut(), 0) };
+    if list_len < 0 {
+        let errno = io::Error::last_os_error().raw_os_error().unwrap_or(0);
+        return if matches!(errno, libc::ENOTSUP | libc::EOPNOTSUPP) {
+            Ok(())
+        } else {
+            Err(CloneError::from_errno("flistxattr source", errno))
+        };
+    }
+    if list_len == 0 {
+        return Ok(());
+    }
+
+    let mut names = vec![0u8; list_len as usize];
+    let list_len =
+        unsafe { flistxattr(src_fd, names.as_mut_ptr() as *mut libc::c_char, names.len()) };
+    if list_len < 0 {
+        let errno = io::Error::last_os_error().raw_os_error().unwrap_or(0);
+        return Err(CloneError::from_errno("flistxattr source", errno));
+    }
+    names.truncate(list_len as usize);
+
+    for name in names.split(|&b| b == 0).filter(|n| !n.is_empty()) {
+        let name = CString::new(name).map_err(|_| CloneError::NullByte)?;
+
+        let value_len = unsafe { fgetxattr(src_fd, name.as_ptr(), std::ptr::null_mut(), 0) };
+        if value_len < 0 {
+            // The attribute vanished (or became unreadable) between the
+            // list and the get; best-effort, so move on to the rest.
+            continue;
+        }
+
+        let mut value = vec![0u8; value_len as usize];
+        let value_len = unsafe {
+            fgetxattr(
+                src_fd,
+                name.as_ptr(),
+                value.as_mut_ptr() as *mut libc::c_void,
+                value.len(),
+            )
+        };
+        if value_len < 0 {
+            continue;
+        }
+        value.truncate(value_len as usize);
+
+        let result = unsafe {
+            fsetxattr(
+                dest_fd,
+                name.as_ptr(),
+                value.as_ptr() as *const libc::c_void,
+                value.len(),
+                0,
+            )
+        };
+        if result != 0 {
+            let errno = io::Error::last_os_error().raw_os_error().unwrap_or(0);
+            if !matches!(errno, libc::ENOTSUP | libc::EOPNOTSUPP) {
+                return Err(CloneError::from_errno("fsetxattr dest", errno));
+            }
+        }
+    }
 
-    // Determine the file type for error message
-    let type_name = if file_type.is_symlink() {
+    Ok(())
+}
+
+/// Apply whichever of `preserve_times`/`preserve_owner`/`preserve_xattrs`
+/// the caller opted into, after a successful clone of `src_file` onto
+/// `dest_file`. The cheap fast path (all three `false`) does nothing here.
+#[allow(clippy::too_many_arguments)]
+fn preserve_metadata(
+    src_file: &File,
+    dest_file: &File,
+    src_stat: &libc::stat,
+    preserve_times_flag: bool,
+    preserve_owner_flag: bool,
+    preserve_xattrs_flag: bool,
+) -> CloneResult<()> {
+    if preserve_times_flag {
+        preserve_times(dest_file, src_stat)?;
+    }
+    if preserve_owner_flag {
+        preserve_owner(dest_file, src_stat)?;
+        // POSIX clears S_ISUID/S_ISGID on a successful chown/fchown, so a
+        // setuid/setgid source would otherwise lose those bits on dest even
+        // though `clone_regular_file_fd` already forced the mode earlier.
+        // Re-apply it now that ownership has settled.
+        enforce_dest_mode(dest_file, src_stat.st_mode)?;
+    }
+    if preserve_xattrs_flag {
+        preserve_xattrs(src_file, dest_file)?;
+    }
+    Ok(())
+}
+
+/// Name a file type (from `st_mode`'s type bits) for error messages.
+/// Callers should have already ruled out regular files and directories.
+fn special_file_type_name_from_mode(mode: u32) -> &'static str {
+    match mode & libc::S_IFMT {
+        libc::S_IFLNK => "symlink",
+        libc::S_IFBLK => "block device",
+        libc::S_IFCHR => "character device",
+        libc::S_IFIFO => "FIFO (named pipe)",
+        libc::S_IFSOCK => "socket",
+        _ => "unknown",
+    }
+}
+
+/// Name a non-regular, non-directory file type for error messages.
+/// Callers should have already ruled out regular files and directories.
+fn special_file_type_name(file_type: &fs::FileType) -> &'static str {
+    if file_type.is_symlink() {
         "symlink"
-    } else if file_type.is_dir() {
-        "directory"
     } else if file_type.is_block_device() {
         "block device"
     } else if file_type.is_char_device() {
@@ -63,9 +393,7 @@ fn validate_file_type(path: &Path) -> CloneResult<()> {
         "socket"
     } else {
         "unknown"
-    };
-
-    Err(CloneError::UnsupportedFileType { file_type: type_name })
+    }
 }
 
 fn validate_path(path: &Path) -> CloneResult<()> {
@@ -77,51 +405,408 @@ fn validate_path(path: &Path) -> CloneResult<()> {
     Ok(())
 }
 
-/// Clone a file using Linux FICLONE ioctl
+/// What to do when a recursive directory clone encounters an entry that is
+/// neither a regular file nor a directory (symlink, device, socket, FIFO).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpecialFilePolicy {
+    /// Abort the whole clone with `UnsupportedFileType`.
+    Reject,
+    /// Leave the entry out of the destination tree and continue.
+    Skip,
+}
+
+/// Clone a file or directory using Linux FICLONE ioctl.
+///
+/// Regular files are cloned directly with FICLONE; directories are cloned
+/// by recursively walking the source and cloning each entry underneath (see
+/// `clone_directory`).
+///
+/// When `allow_copy_fallback` is `true` and FICLONE can't be used (the
+/// filesystem doesn't support it, or the clone would cross filesystems),
+/// falls back to `copy_file_range(2)`, and if that also isn't usable, to a
+/// buffered read/write loop. When `false`, any FICLONE failure is returned
+/// to the caller as-is so only true copy-on-write clones succeed.
+///
+/// `skip_special_files` controls what happens when a symlink, device,
+/// socket, or FIFO is encountered while walking a directory source (it has
+/// no effect when `src` is itself one of those, which is always rejected).
+///
+/// Returns the `CloneMethod` that was actually used. For a directory clone
+/// this is the "weakest" method used by any entry underneath (e.g. if one
+/// file fell all the way back to a buffered copy, the whole clone reports
+/// `Buffered`), so callers can tell whether the *entire* tree was a true
+/// copy-on-write clone.
+///
+/// `_clone_follow_symlinks`/`_preserve_ownership` are macOS `clonefile()`
+/// flags with no FICLONE equivalent; they exist only to keep this
+/// function's signature aligned with the Darwin implementation so `lib.rs`
+/// can call `platform::clone_file` without per-OS branches.
+///
+/// `preserve_times`/`preserve_owner`/`preserve_xattrs` are opt-in,
+/// default-`false` flags applied after a regular file's data has been
+/// cloned; see the module's "Metadata preservation" note.
 ///
 /// # Security
-/// - Validates source file type before cloning (rejects symlinks, devices, etc.)
-pub fn clone_file(src: &Path, dest: &Path) -> CloneResult<()> {
+/// - Opens `src` with `O_NOFOLLOW` and determines its type via `fstat` on
+///   that descriptor (rather than `stat`-ing the path and opening it
+///   separately), so a symlink swapped in after the check can't be followed
+///   (see the module's TOCTOU note).
+#[allow(clippy::too_many_arguments)]
+pub fn clone_file(
+    src: &Path,
+    dest: &Path,
+    allow_copy_fallback: bool,
+    skip_special_files: bool,
+    _clone_follow_symlinks: bool,
+    _preserve_ownership: bool,
+    preserve_times: bool,
+    preserve_owner: bool,
+    preserve_xattrs: bool,
+) -> CloneResult<CloneMethod> {
     validate_path(src)?;
     validate_path(dest)?;
 
-    // SECURITY: Validate file type before cloning
-    // This prevents symlink attacks, device file access, and socket/FIFO exploitation
-    validate_file_type(src)?;
-
-    // Open source file for reading
-    let src_file = File::open(src).map_err(|e| {
-        CloneError::from_errno("open source", e.raw_os_error().unwrap_or(0))
-    })?;
-
-    // Get source file permissions to preserve them
-    let src_metadata = std::fs::metadata(src).map_err(|e| {
-        CloneError::from_errno("stat source", e.raw_os_error().unwrap_or(0))
-    })?;
-    let src_mode = src_metadata.permissions().mode();
-
-    // Create/open destination file for writing with source permissions
-    let dest_file = OpenOptions::new()
-        .write(true)
-        .create(true)
-        .truncate(true)
-        .mode(src_mode)
-        .open(dest)
-        .map_err(|e| {
-            CloneError::from_errno("open dest", e.raw_os_error().unwrap_or(0))
-        })?;
+    // SECURITY (TOCTOU): open once with O_NOFOLLOW, then fstat that exact
+    // descriptor rather than stat-ing the path and opening it again.
+    let src_file = open_nofollow(src)?;
+    let src_stat = fstat_file(&src_file)
+        .map_err(|e| CloneError::from_errno("fstat source", e.raw_os_error().unwrap_or(0)))?;
+    let file_type_mode = src_stat.st_mode & libc::S_IFMT;
+
+    if file_type_mode == libc::S_IFDIR {
+        // The descriptor served only to confirm the type without a race;
+        // the recursive walk below works on paths.
+        drop(src_file);
+        let policy = if skip_special_files {
+            SpecialFilePolicy::Skip
+        } else {
+            SpecialFilePolicy::Reject
+        };
+        return clone_directory(
+            src,
+            dest,
+            allow_copy_fallback,
+            policy,
+            preserve_times,
+            preserve_owner,
+            preserve_xattrs,
+        );
+    }
+
+    if file_type_mode != libc::S_IFREG {
+        return Err(CloneError::UnsupportedFileType {
+            file_type: special_file_type_name_from_mode(file_type_mode),
+        });
+    }
+
+    clone_regular_file_fd(
+        src_file,
+        &src_stat,
+        dest,
+        allow_copy_fallback,
+        preserve_times,
+        preserve_owner,
+        preserve_xattrs,
+    )
+}
+
+/// Clone a single regular file using FICLONE (with optional copy fallback).
+/// Opens `src` itself with `O_NOFOLLOW`; callers that already have an open,
+/// `fstat`-ed source descriptor (e.g. `clone_file`'s own dispatch) should
+/// call `clone_regular_file_fd` directly instead of reopening it.
+#[allow(clippy::too_many_arguments)]
+fn clone_regular_file(
+    src: &Path,
+    dest: &Path,
+    allow_copy_fallback: bool,
+    preserve_times: bool,
+    preserve_owner: bool,
+    preserve_xattrs: bool,
+) -> CloneResult<CloneMethod> {
+    let src_file = open_nofollow(src)?;
+    let src_stat = fstat_file(&src_file)
+        .map_err(|e| CloneError::from_errno("fstat source", e.raw_os_error().unwrap_or(0)))?;
+
+    if src_stat.st_mode & libc::S_IFMT != libc::S_IFREG {
+        return Err(CloneError::UnsupportedFileType {
+            file_type: special_file_type_name_from_mode(src_stat.st_mode & libc::S_IFMT),
+        });
+    }
+
+    clone_regular_file_fd(
+        src_file,
+        &src_stat,
+        dest,
+        allow_copy_fallback,
+        preserve_times,
+        preserve_owner,
+        preserve_xattrs,
+    )
+}
+
+/// Monotonic counter folded into temp-file names (alongside the pid) so
+/// concurrent clones from this process landing in the same directory don't
+/// collide; see `temp_dest_path`.
+static TEMP_NAME_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Build a sibling path for `dest` to clone into before the final `rename`,
+/// living in the same directory so that rename stays on one filesystem
+/// (required for it to be atomic) and named so two clones running in this
+/// process at once don't pick the same path.
+fn temp_dest_path(dest: &Path, attempt: u64) -> CloneResult<PathBuf> {
+    let file_name = dest.file_name().ok_or(CloneError::EmptyPath)?;
+    let counter = TEMP_NAME_COUNTER.fetch_add(1, Ordering::Relaxed);
+
+    let mut temp_name = std::ffi::OsString::from(".");
+    temp_name.push(file_name);
+    temp_name.push(format!(".vibe-tmp-{}-{}-{}", std::process::id(), counter, attempt));
+    Ok(dest.with_file_name(temp_name))
+}
+
+/// Create a fresh, exclusive temp file next to `dest` for `clone_regular_file_fd`
+/// to clone into, retrying under a new name on the (astronomically unlikely)
+/// chance of a collision with a leftover or concurrent temp file.
+fn create_temp_dest(dest: &Path, src_mode: u32) -> CloneResult<(File, PathBuf)> {
+    const MAX_ATTEMPTS: u64 = 8;
+
+    let mut last_err = None;
+    for attempt in 0..MAX_ATTEMPTS {
+        let temp_path = temp_dest_path(dest, attempt)?;
+        let result = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(src_mode)
+            .custom_flags(libc::O_NOFOLLOW | libc::O_CLOEXEC)
+            .open(&temp_path);
+
+        match result {
+            Ok(file) => return Ok((file, temp_path)),
+            Err(e) if e.raw_os_error() == Some(libc::EEXIST) => {
+                last_err = Some(e);
+                continue;
+            }
+            Err(e) => return Err(map_open_errno("open temp dest", e.raw_os_error().unwrap_or(0))),
+        }
+    }
+
+    Err(map_open_errno(
+        "open temp dest",
+        last_err.and_then(|e| e.raw_os_error()).unwrap_or(0),
+    ))
+}
+
+/// Core of `clone_regular_file`, taking an already-opened and `fstat`-ed
+/// source descriptor so the dispatch in `clone_file` doesn't have to open
+/// `src` a second time (which would reopen the TOCTOU window it just closed).
+///
+/// # Security
+/// `dest` itself is never opened: a destination path that resolves to an
+/// existing symlink is rejected up front (matching the `O_NOFOLLOW` ELOOP
+/// behavior a direct open would have given) rather than followed or
+/// silently replaced by the final rename.
+///
+/// # Atomicity
+/// See the module's "Atomicity" note: the clone (and any requested
+/// metadata preservation) runs entirely against a sibling temp file, which
+/// is renamed onto `dest` only once everything has succeeded. Any failure
+/// along the way removes the temp file and leaves `dest` exactly as it was.
+#[allow(clippy::too_many_arguments)]
+fn clone_regular_file_fd(
+    src_file: File,
+    src_stat: &libc::stat,
+    dest: &Path,
+    allow_copy_fallback: bool,
+    preserve_times_flag: bool,
+    preserve_owner_flag: bool,
+    preserve_xattrs_flag: bool,
+) -> CloneResult<CloneMethod> {
+    if matches!(dest.symlink_metadata(), Ok(meta) if meta.file_type().is_symlink()) {
+        return Err(CloneError::UnsupportedFileType {
+            file_type: "symlink",
+        });
+    }
+
+    let (temp_file, temp_dest) = create_temp_dest(dest, src_stat.st_mode)?;
+
+    let result = clone_regular_file_into(
+        &src_file,
+        src_stat,
+        &temp_file,
+        allow_copy_fallback,
+        preserve_times_flag,
+        preserve_owner_flag,
+        preserve_xattrs_flag,
+    );
+
+    let method = match result {
+        Ok(method) => method,
+        Err(e) => {
+            let _ = std::fs::remove_file(&temp_dest);
+            return Err(e);
+        }
+    };
+
+    if let Err(e) = fs::rename(&temp_dest, dest) {
+        let _ = std::fs::remove_file(&temp_dest);
+        return Err(CloneError::from_errno(
+            "rename temp dest",
+            e.raw_os_error().unwrap_or(0),
+        ));
+    }
+
+    Ok(method)
+}
+
+/// Clone `src_file`'s data onto the already-created, empty `dest_file`
+/// (FICLONE, falling back to `copy_file_range`/a buffered copy per
+/// `allow_copy_fallback`) and apply any opted-in metadata preservation.
+/// Factored out of `clone_regular_file_fd` so that function's only job is
+/// managing the temp file/rename around this.
+#[allow(clippy::too_many_arguments)]
+fn clone_regular_file_into(
+    src_file: &File,
+    src_stat: &libc::stat,
+    dest_file: &File,
+    allow_copy_fallback: bool,
+    preserve_times_flag: bool,
+    preserve_owner_flag: bool,
+    preserve_xattrs_flag: bool,
+) -> CloneResult<CloneMethod> {
+    let src_mode = src_stat.st_mode;
+    let src_len = src_stat.st_size as u64;
+
+    enforce_dest_mode(dest_file, src_mode)?;
 
     // Perform FICLONE ioctl
     let result = unsafe { ficlone(dest_file.as_raw_fd(), src_file.as_raw_fd() as i32) };
 
-    if let Err(errno) = result {
-        // Remove partially created destination file on failure
-        let _ = std::fs::remove_file(dest);
+    let method = if let Err(errno) = result {
         let errno_value: i32 = errno.into();
-        return Err(CloneError::from_errno("ioctl FICLONE", errno_value));
+
+        if !allow_copy_fallback || !is_ficlone_fallback_errno(errno_value) {
+            return Err(CloneError::from_errno("ioctl FICLONE", errno_value));
+        }
+
+        // Fall back to copy_file_range, and if that's not usable either
+        // (ENOSYS: no kernel support; EXDEV: cross-device on kernels too old
+        // to copy_file_range across devices; EINVAL: dest is a type
+        // copy_file_range rejects, e.g. a FIFO, or the filesystem doesn't
+        // implement it), a plain buffered copy.
+        let method = match copy_file_range_loop(src_file, dest_file, src_len) {
+            Ok(()) => Ok(CloneMethod::CopyFileRange),
+            Err(e)
+                if matches!(
+                    e.raw_os_error(),
+                    Some(libc::ENOSYS) | Some(libc::EXDEV) | Some(libc::EINVAL)
+                ) =>
+            {
+                buffered_copy(src_file, dest_file).map(|()| CloneMethod::Buffered)
+            }
+            Err(e) => Err(e),
+        };
+
+        match method {
+            Ok(method) => method,
+            Err(e) => {
+                return Err(CloneError::from_errno(
+                    "copy fallback",
+                    e.raw_os_error().unwrap_or(0),
+                ));
+            }
+        }
+    } else {
+        CloneMethod::Ficlone
+    };
+
+    preserve_metadata(
+        src_file,
+        dest_file,
+        src_stat,
+        preserve_times_flag,
+        preserve_owner_flag,
+        preserve_xattrs_flag,
+    )?;
+
+    Ok(method)
+}
+
+/// Recursively clone a directory: recreate the directory tree on the
+/// destination side (preserving each directory's mode), FICLONE each
+/// regular file, and apply `policy` to any symlink/device/socket/FIFO
+/// encountered along the way.
+///
+/// # Security
+/// Every entry visited during the walk is re-validated against
+/// `special_file_type_name`, and each regular file is re-opened with
+/// `O_NOFOLLOW` by `clone_regular_file`, so a symlink planted inside the
+/// source tree after the top-level check can't be followed.
+#[allow(clippy::too_many_arguments)]
+fn clone_directory(
+    src: &Path,
+    dest: &Path,
+    allow_copy_fallback: bool,
+    policy: SpecialFilePolicy,
+    preserve_times: bool,
+    preserve_owner: bool,
+    preserve_xattrs: bool,
+) -> CloneResult<CloneMethod> {
+    let src_mode = fs::metadata(src)
+        .map_err(|e| CloneError::from_errno("stat source", e.raw_os_error().unwrap_or(0)))?
+        .permissions()
+        .mode();
+
+    fs::create_dir(dest)
+        .map_err(|e| CloneError::from_errno("mkdir dest", e.raw_os_error().unwrap_or(0)))?;
+    fs::set_permissions(dest, fs::Permissions::from_mode(src_mode))
+        .map_err(|e| CloneError::from_errno("chmod dest", e.raw_os_error().unwrap_or(0)))?;
+
+    let entries = fs::read_dir(src)
+        .map_err(|e| CloneError::from_errno("readdir source", e.raw_os_error().unwrap_or(0)))?;
+
+    let mut weakest_method = CloneMethod::Ficlone;
+
+    for entry in entries {
+        let entry = entry
+            .map_err(|e| CloneError::from_errno("readdir source", e.raw_os_error().unwrap_or(0)))?;
+        let entry_src = entry.path();
+        let entry_dest = dest.join(entry.file_name());
+        let file_type = entry
+            .file_type()
+            .map_err(|e| CloneError::from_errno("stat", e.raw_os_error().unwrap_or(0)))?;
+
+        let entry_method = if file_type.is_dir() {
+            clone_directory(
+                &entry_src,
+                &entry_dest,
+                allow_copy_fallback,
+                policy,
+                preserve_times,
+                preserve_owner,
+                preserve_xattrs,
+            )?
+        } else if file_type.is_file() {
+            clone_regular_file(
+                &entry_src,
+                &entry_dest,
+                allow_copy_fallback,
+                preserve_times,
+                preserve_owner,
+                preserve_xattrs,
+            )?
+        } else {
+            match policy {
+                SpecialFilePolicy::Reject => {
+                    return Err(CloneError::UnsupportedFileType {
+                        file_type: special_file_type_name(&file_type),
+                    });
+                }
+                SpecialFilePolicy::Skip => continue,
+            }
+        };
+        weakest_method = weakest_method.max(entry_method);
     }
 
-    Ok(())
+    Ok(weakest_method)
 }
 
 /// Check if FICLONE is available
@@ -130,9 +815,10 @@ pub fn is_available() -> bool {
     true
 }
 
-/// Check if directory cloning is supported (false for FICLONE)
+/// Check if directory cloning is supported.
+/// True: directories are cloned by recursively FICLONE-ing their contents.
 pub fn supports_directory() -> bool {
-    false
+    true
 }
 
 /// Get platform name
@@ -155,7 +841,7 @@ mod tests {
 
     #[test]
     fn test_supports_directory() {
-        assert!(!supports_directory());
+        assert!(supports_directory());
     }
 
     #[test]
@@ -173,16 +859,17 @@ mod tests {
         fs::write(&src, "test content").unwrap();
 
         // Clone
-        let result = clone_file(&src, &dest);
+        let result = clone_file(&src, &dest, false, false, true, true, false, false, false);
 
         // Btrfs/XFS with reflink is required for FICLONE to work
         // On other filesystems, it may fail with EOPNOTSUPP
         match result {
-            Ok(()) => {
+            Ok(CloneMethod::Ficlone) => {
                 // Success - file was cloned
                 let content = fs::read_to_string(&dest).unwrap();
                 assert_eq!(content, "test content");
             }
+            Ok(method) => panic!("Unexpected clone method: {:?}", method),
             Err(CloneError::SystemError { errno, .. })
                 if errno == libc::EOPNOTSUPP || errno == libc::ENOTSUP =>
             {
@@ -193,13 +880,40 @@ mod tests {
         // TempDir automatically cleans up on drop
     }
 
+    #[test]
+    fn test_clone_file_with_copy_fallback() {
+        let temp_dir = TempDir::new().unwrap();
+        let src = temp_dir.path().join("src.txt");
+        let dest = temp_dir.path().join("dest.txt");
+
+        fs::write(&src, "test content").unwrap();
+
+        // Whatever the temp filesystem supports, allowing the copy fallback
+        // must still produce a correct clone (FICLONE, copy_file_range, or
+        // a buffered copy).
+        let result = clone_file(&src, &dest, true, false, true, true, false, false, false);
+        assert!(result.is_ok(), "Expected clone to succeed: {:?}", result);
+        let content = fs::read_to_string(&dest).unwrap();
+        assert_eq!(content, "test content");
+    }
+
     #[test]
     fn test_empty_source_path() {
         let temp_dir = TempDir::new().unwrap();
         let empty_path = PathBuf::from("");
         let dest = temp_dir.path().join("dest.txt");
 
-        let result = clone_file(&empty_path, &dest);
+        let result = clone_file(
+            &empty_path,
+            &dest,
+            false,
+            false,
+            true,
+            true,
+            false,
+            false,
+            false,
+        );
         let is_empty_path_error = matches!(result, Err(CloneError::EmptyPath));
         assert!(is_empty_path_error, "Expected EmptyPath error");
     }
@@ -213,7 +927,17 @@ mod tests {
         // Create source file
         fs::write(&src, "test content").unwrap();
 
-        let result = clone_file(&src, &empty_path);
+        let result = clone_file(
+            &src,
+            &empty_path,
+            false,
+            false,
+            true,
+            true,
+            false,
+            false,
+            false,
+        );
         let is_empty_path_error = matches!(result, Err(CloneError::EmptyPath));
         assert!(is_empty_path_error, "Expected EmptyPath error");
     }
@@ -224,7 +948,17 @@ mod tests {
         let nonexistent = temp_dir.path().join("nonexistent.txt");
         let dest = temp_dir.path().join("dest.txt");
 
-        let result = clone_file(&nonexistent, &dest);
+        let result = clone_file(
+            &nonexistent,
+            &dest,
+            false,
+            false,
+            true,
+            true,
+            false,
+            false,
+            false,
+        );
         let is_system_error = matches!(
             result,
             Err(CloneError::SystemError { errno, .. }) if errno == libc::ENOENT
@@ -250,7 +984,7 @@ mod tests {
         fs::set_permissions(&readonly_dir, perms).unwrap();
 
         let dest = readonly_dir.join("dest.txt");
-        let result = clone_file(&src, &dest);
+        let result = clone_file(&src, &dest, false, false, true, true, false, false, false);
 
         // Restore permissions for cleanup
         let mut perms = fs::metadata(&readonly_dir).unwrap().permissions();
@@ -279,10 +1013,10 @@ mod tests {
         perms.set_mode(0o755);
         fs::set_permissions(&src, perms).unwrap();
 
-        let result = clone_file(&src, &dest);
+        let result = clone_file(&src, &dest, false, false, true, true, false, false, false);
 
         match result {
-            Ok(()) => {
+            Ok(_method) => {
                 // Verify permissions are preserved
                 let src_mode = fs::metadata(&src).unwrap().permissions().mode();
                 let dest_mode = fs::metadata(&dest).unwrap().permissions().mode();
@@ -315,7 +1049,7 @@ mod tests {
         symlink(&src, &link).unwrap();
 
         // Attempt to clone symlink should fail
-        let result = clone_file(&link, &dest);
+        let result = clone_file(&link, &dest, false, false, true, true, false, false, false);
 
         match result {
             Err(CloneError::UnsupportedFileType { file_type }) => {
@@ -326,36 +1060,80 @@ mod tests {
     }
 
     #[test]
-    fn test_reject_directory() {
+    fn test_clone_directory_recursive() {
         let temp_dir = TempDir::new().unwrap();
         let src_dir = temp_dir.path().join("src_dir");
         let dest_dir = temp_dir.path().join("dest_dir");
 
-        // Create source directory
         fs::create_dir(&src_dir).unwrap();
+        fs::write(src_dir.join("a.txt"), "a").unwrap();
+        fs::create_dir(src_dir.join("nested")).unwrap();
+        fs::write(src_dir.join("nested").join("b.txt"), "b").unwrap();
+
+        let result = clone_file(
+            &src_dir, &dest_dir, true, false, true, true, false, false, false,
+        );
+        assert!(result.is_ok(), "Expected clone to succeed: {:?}", result);
+
+        assert_eq!(fs::read_to_string(dest_dir.join("a.txt")).unwrap(), "a");
+        assert_eq!(
+            fs::read_to_string(dest_dir.join("nested").join("b.txt")).unwrap(),
+            "b"
+        );
+    }
+
+    #[test]
+    fn test_clone_directory_rejects_special_file_by_default() {
+        let temp_dir = TempDir::new().unwrap();
+        let src_dir = temp_dir.path().join("src_dir");
+        let dest_dir = temp_dir.path().join("dest_dir");
 
-        // Attempt to clone directory should fail (FICLONE only supports files)
-        let result = clone_file(&src_dir, &dest_dir);
+        fs::create_dir(&src_dir).unwrap();
+        fs::write(src_dir.join("a.txt"), "a").unwrap();
+        symlink(src_dir.join("a.txt"), src_dir.join("link.txt")).unwrap();
 
+        let result = clone_file(
+            &src_dir, &dest_dir, true, false, true, true, false, false, false,
+        );
         match result {
             Err(CloneError::UnsupportedFileType { file_type }) => {
-                assert_eq!(file_type, "directory");
+                assert_eq!(file_type, "symlink");
             }
-            _ => panic!("Expected UnsupportedFileType error for directory"),
+            _ => panic!("Expected UnsupportedFileType error for symlink entry"),
         }
     }
 
     #[test]
-    fn test_validate_file_type_regular_file() {
+    fn test_clone_directory_skips_special_file_when_requested() {
+        let temp_dir = TempDir::new().unwrap();
+        let src_dir = temp_dir.path().join("src_dir");
+        let dest_dir = temp_dir.path().join("dest_dir");
+
+        fs::create_dir(&src_dir).unwrap();
+        fs::write(src_dir.join("a.txt"), "a").unwrap();
+        symlink(src_dir.join("a.txt"), src_dir.join("link.txt")).unwrap();
+
+        let result = clone_file(
+            &src_dir, &dest_dir, true, true, true, true, false, false, false,
+        );
+        assert!(result.is_ok(), "Expected clone to succeed: {:?}", result);
+        assert_eq!(fs::read_to_string(dest_dir.join("a.txt")).unwrap(), "a");
+        assert!(!dest_dir.join("link.txt").exists());
+    }
+
+    #[test]
+    fn test_open_nofollow_regular_file() {
         let temp_dir = TempDir::new().unwrap();
         let src = temp_dir.path().join("src.txt");
         fs::write(&src, "test").unwrap();
 
-        assert!(validate_file_type(&src).is_ok());
+        let file = open_nofollow(&src).unwrap();
+        let stat = fstat_file(&file).unwrap();
+        assert_eq!(stat.st_mode & libc::S_IFMT, libc::S_IFREG);
     }
 
     #[test]
-    fn test_validate_file_type_symlink() {
+    fn test_open_nofollow_rejects_symlink() {
         let temp_dir = TempDir::new().unwrap();
         let src = temp_dir.path().join("src.txt");
         let link = temp_dir.path().join("link.txt");
@@ -363,23 +1141,234 @@ mod tests {
         fs::write(&src, "test").unwrap();
         symlink(&src, &link).unwrap();
 
-        let result = validate_file_type(&link);
+        let result = open_nofollow(&link);
         assert!(matches!(
             result,
-            Err(CloneError::UnsupportedFileType { file_type: "symlink" })
+            Err(CloneError::UnsupportedFileType {
+                file_type: "symlink"
+            })
         ));
     }
 
     #[test]
-    fn test_validate_file_type_directory() {
+    fn test_open_nofollow_directory() {
         let temp_dir = TempDir::new().unwrap();
         let dir = temp_dir.path().join("dir");
         fs::create_dir(&dir).unwrap();
 
-        let result = validate_file_type(&dir);
+        let file = open_nofollow(&dir).unwrap();
+        let stat = fstat_file(&file).unwrap();
+        assert_eq!(stat.st_mode & libc::S_IFMT, libc::S_IFDIR);
+    }
+
+    #[test]
+    fn test_clone_rejects_symlink_swapped_in_for_destination() {
+        let temp_dir = TempDir::new().unwrap();
+        let src = temp_dir.path().join("src.txt");
+        let elsewhere = temp_dir.path().join("elsewhere.txt");
+        let dest_link = temp_dir.path().join("dest_link.txt");
+
+        fs::write(&src, "test content").unwrap();
+        fs::write(&elsewhere, "do not overwrite me").unwrap();
+        symlink(&elsewhere, &dest_link).unwrap();
+
+        // Cloning over a destination that is itself a symlink must fail
+        // rather than silently writing through it to `elsewhere.txt`.
+        let result = clone_file(
+            &src, &dest_link, false, false, true, true, false, false, false,
+        );
         assert!(matches!(
             result,
-            Err(CloneError::UnsupportedFileType { file_type: "directory" })
+            Err(CloneError::UnsupportedFileType {
+                file_type: "symlink"
+            })
         ));
+        assert_eq!(
+            fs::read_to_string(&elsewhere).unwrap(),
+            "do not overwrite me"
+        );
+    }
+
+    #[test]
+    fn test_clone_file_preserve_times() {
+        let temp_dir = TempDir::new().unwrap();
+        let src = temp_dir.path().join("src.txt");
+        let dest = temp_dir.path().join("dest.txt");
+        fs::write(&src, "test content").unwrap();
+
+        let old_time = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+        let times = fs::FileTimes::new()
+            .set_accessed(old_time)
+            .set_modified(old_time);
+        File::open(&src).unwrap().set_times(times).unwrap();
+
+        clone_file(&src, &dest, false, false, true, true, true, false, false).unwrap();
+
+        let dest_mtime = fs::metadata(&dest).unwrap().modified().unwrap();
+        assert_eq!(dest_mtime, old_time);
+    }
+
+    #[test]
+    fn test_clone_file_without_preserve_times_uses_clone_time() {
+        let temp_dir = TempDir::new().unwrap();
+        let src = temp_dir.path().join("src.txt");
+        let dest = temp_dir.path().join("dest.txt");
+        fs::write(&src, "test content").unwrap();
+
+        let old_time = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+        let times = fs::FileTimes::new()
+            .set_accessed(old_time)
+            .set_modified(old_time);
+        File::open(&src).unwrap().set_times(times).unwrap();
+
+        clone_file(&src, &dest, false, false, true, true, false, false, false).unwrap();
+
+        let dest_mtime = fs::metadata(&dest).unwrap().modified().unwrap();
+        assert_ne!(dest_mtime, old_time);
+    }
+
+    #[test]
+    fn test_clone_file_preserve_xattrs() {
+        let temp_dir = TempDir::new().unwrap();
+        let src = temp_dir.path().join("src.txt");
+        let dest = temp_dir.path().join("dest.txt");
+        fs::write(&src, "test content").unwrap();
+
+        let src_file = File::open(&src).unwrap();
+        let name = CString::new("user.test").unwrap();
+        let value = b"hello";
+        let set_result = unsafe {
+            fsetxattr(
+                src_file.as_raw_fd(),
+                name.as_ptr(),
+                value.as_ptr() as *const libc::c_void,
+                value.len(),
+                0,
+            )
+        };
+        if set_result != 0 {
+            // The temp filesystem doesn't support xattrs in this environment;
+            // nothing to verify.
+            return;
+        }
+
+        clone_file(&src, &dest, false, false, true, true, false, false, true).unwrap();
+
+        let dest_file = File::open(&dest).unwrap();
+        let mut buf = vec![0u8; value.len()];
+        let got = unsafe {
+            fgetxattr(
+                dest_file.as_raw_fd(),
+                name.as_ptr(),
+                buf.as_mut_ptr() as *mut libc::c_void,
+                buf.len(),
+            )
+        };
+        assert_eq!(got, value.len() as isize);
+        assert_eq!(&buf, value);
+    }
+
+    #[test]
+    fn test_clone_file_keeps_preexisting_dest_on_ficlone_failure() {
+        let temp_dir = TempDir::new().unwrap();
+        let src = temp_dir.path().join("src.txt");
+        let dest = temp_dir.path().join("dest.txt");
+        fs::write(&src, "test content").unwrap();
+        fs::write(&dest, "old content").unwrap();
+
+        // Without the copy fallback, an unsupported FICLONE is a hard error;
+        // the pre-existing dest must still have its original content
+        // afterwards, not be removed *or* left truncated by the failed
+        // attempt (the clone runs against a temp file, never `dest` itself).
+        let result = clone_file(&src, &dest, false, false, true, true, false, false, false);
+
+        match result {
+            Err(CloneError::SystemError { errno, .. })
+                if errno == libc::EOPNOTSUPP || errno == libc::ENOTSUP =>
+            {
+                assert_eq!(
+                    fs::read_to_string(&dest).unwrap(),
+                    "old content",
+                    "pre-existing dest must be untouched, not truncated"
+                );
+            }
+            Ok(_) => {
+                // FICLONE succeeded on this filesystem - nothing to check here.
+            }
+            Err(e) => panic!("Unexpected error: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn test_clone_file_leaves_no_temp_file_behind() {
+        let temp_dir = TempDir::new().unwrap();
+        let src = temp_dir.path().join("src.txt");
+        let dest = temp_dir.path().join("dest.txt");
+        fs::write(&src, "test content").unwrap();
+
+        clone_file(&src, &dest, true, false, true, true, false, false, false).unwrap();
+
+        let mut entries: Vec<_> = fs::read_dir(temp_dir.path())
+            .unwrap()
+            .map(|e| e.unwrap().file_name())
+            .collect();
+        entries.sort();
+        assert_eq!(
+            entries,
+            vec![
+                std::ffi::OsString::from("dest.txt"),
+                std::ffi::OsString::from("src.txt"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_clone_file_corrects_stale_dest_permissions() {
+        let temp_dir = TempDir::new().unwrap();
+        let src = temp_dir.path().join("src.txt");
+        let dest = temp_dir.path().join("dest.txt");
+        fs::write(&src, "test content").unwrap();
+        let mut src_perms = fs::metadata(&src).unwrap().permissions();
+        src_perms.set_mode(0o600);
+        fs::set_permissions(&src, src_perms).unwrap();
+
+        // Pre-existing destination with a more permissive, stale mode. The
+        // clone replaces it outright (temp file + rename), so this is really
+        // checking that the replacement lands with exactly `src`'s mode
+        // rather than something umask-narrowed from `OpenOptions::mode`.
+        fs::write(&dest, "old content").unwrap();
+        let mut dest_perms = fs::metadata(&dest).unwrap().permissions();
+        dest_perms.set_mode(0o666);
+        fs::set_permissions(&dest, dest_perms).unwrap();
+
+        clone_file(&src, &dest, false, false, true, true, false, false, false).unwrap();
+
+        let dest_mode = fs::metadata(&dest).unwrap().permissions().mode();
+        assert_eq!(dest_mode & 0o777, 0o600);
+    }
+
+    #[test]
+    fn test_clone_file_preserves_setuid_bit_with_preserve_owner() {
+        let temp_dir = TempDir::new().unwrap();
+        let src = temp_dir.path().join("src.txt");
+        let dest = temp_dir.path().join("dest.txt");
+        fs::write(&src, "test content").unwrap();
+
+        let mut src_perms = fs::metadata(&src).unwrap().permissions();
+        src_perms.set_mode(0o4755); // rwxr-xr-x + setuid
+        fs::set_permissions(&src, src_perms).unwrap();
+
+        // preserve_owner is a no-op fchown to our own uid/gid (unprivileged
+        // processes can't chown to anyone else), but it still exercises the
+        // fchown call whose POSIX side effect (clearing S_ISUID/S_ISGID) is
+        // what this test guards against.
+        clone_file(&src, &dest, true, false, true, true, false, true, false).unwrap();
+
+        let dest_mode = fs::metadata(&dest).unwrap().permissions().mode();
+        assert_eq!(
+            dest_mode & 0o7777,
+            0o4755,
+            "setuid bit must survive preserve_owner's fchown"
+        );
     }
 }
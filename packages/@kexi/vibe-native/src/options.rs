@@ -0,0 +1,31 @@
+//! Platform-specific knobs for `clone_sync`/`clone_async` that don't fit
+//! neatly as plain boolean parameters because they only apply to one OS.
+
+use napi_derive::napi;
+
+/// macOS-only `clonefile()` flags, exposed so callers can opt into
+/// behavior the underlying syscall supports but this crate didn't
+/// previously surface. Ignored on Linux.
+#[napi(object)]
+#[derive(Debug, Clone, Copy)]
+pub struct CloneOptions {
+    /// When `false`, pass `CLONE_NOFOLLOW` so a symlink source is cloned as
+    /// a symlink instead of being rejected outright. Defaults to `true`
+    /// (symlink sources are rejected, the crate's long-standing default).
+    pub clone_follow_symlinks: Option<bool>,
+    /// When `false`, pass `CLONE_NOOWNERCOPY` so the clone is owned by the
+    /// calling user/group instead of copying the source's owner/group/
+    /// SUID/SGID bits — useful when cloning into a sandbox as an
+    /// unprivileged user. Defaults to `true` (`clonefile()`'s own default).
+    pub preserve_ownership: Option<bool>,
+}
+
+impl CloneOptions {
+    pub fn clone_follow_symlinks(&self) -> bool {
+        self.clone_follow_symlinks.unwrap_or(true)
+    }
+
+    pub fn preserve_ownership(&self) -> bool {
+        self.preserve_ownership.unwrap_or(true)
+    }
+}
@@ -0,0 +1,29 @@
+//! Shared types for reporting which strategy a clone actually used.
+
+use napi_derive::napi;
+
+/// Which underlying strategy performed a clone.
+///
+/// Platform modules return this from their `clone_file` so callers that
+/// want to know (rather than just succeed/fail) can tell a true
+/// copy-on-write clone apart from a fallback byte copy.
+#[napi(string_enum)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CloneMethod {
+    #[napi(value = "clonefile")]
+    Clonefile,
+    #[napi(value = "ficlone")]
+    Ficlone,
+    #[napi(value = "copy_file_range")]
+    CopyFileRange,
+    #[napi(value = "fcopyfile")]
+    Fcopyfile,
+    #[napi(value = "buffered")]
+    Buffered,
+}
+
+/// Result of `clone_with_outcome`/`clone_with_outcome_async`.
+#[napi(object)]
+pub struct CloneOutcome {
+    pub method: CloneMethod,
+}
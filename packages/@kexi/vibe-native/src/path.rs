@@ -0,0 +1,151 @@
+//! Lexical path normalization and opt-in sandbox enforcement.
+//!
+//! `Path::canonicalize()` can't validate a not-yet-created destination path
+//! (it requires the path to exist), and it resolves symlinks in ways that
+//! can surprise callers reasoning about where a path "is" rather than where
+//! it points. `normalize_path` instead collapses `.`/`..` components purely
+//! lexically, without touching the filesystem, so it works for destinations
+//! that don't exist yet as well as existing sources.
+
+use crate::error::{CloneError, CloneResult};
+use std::path::{Component, Path, PathBuf};
+
+/// Lexically normalize `path`, collapsing `.` and `..` components without
+/// touching the filesystem.
+///
+/// Adapted from cargo-util's `paths::normalize_path`: `Prefix`/`RootDir`
+/// components are pushed as-is, `CurDir` is skipped, `ParentDir` pops the
+/// last pushed `Normal` component (or is pushed literally when there's
+/// nothing to pop, e.g. a leading `..` in a relative path), and `Normal`
+/// components are pushed verbatim.
+pub fn normalize_path(path: &Path) -> PathBuf {
+    let mut components = path.components().peekable();
+    let mut normalized =
+        if let Some(component @ Component::Prefix(..)) = components.peek().copied() {
+            components.next();
+            PathBuf::from(component.as_os_str())
+        } else {
+            PathBuf::new()
+        };
+
+    for component in components {
+        match component {
+            Component::Prefix(..) => unreachable!(),
+            Component::RootDir => normalized.push(component.as_os_str()),
+            Component::CurDir => {}
+            Component::ParentDir => match normalized.components().last() {
+                Some(Component::Normal(_)) => {
+                    normalized.pop();
+                }
+                _ => normalized.push(".."),
+            },
+            Component::Normal(c) => normalized.push(c),
+        }
+    }
+
+    normalized
+}
+
+/// Ensure that both `src` and `dest`, once lexically normalized, fall inside
+/// `sandbox_root`. Intended as an opt-in guard for callers who want this
+/// crate to enforce the path traversal protection its docs otherwise leave
+/// entirely up to the caller.
+///
+/// `sandbox_root` must already exist; it is canonicalized so that e.g. a
+/// symlinked sandbox root still matches paths normalized to the real
+/// location. `src`/`dest` are only normalized lexically (not canonicalized),
+/// since the destination may not exist yet.
+///
+/// # Security
+/// Rejects any `src`/`dest` whose normalized form escapes `sandbox_root`,
+/// e.g. via a `..` component or an absolute path pointing elsewhere.
+pub fn ensure_in_sandbox(src: &Path, dest: &Path, sandbox_root: &Path) -> CloneResult<()> {
+    let canonical_root = sandbox_root.canonicalize().map_err(|e| {
+        CloneError::from_errno("canonicalize sandbox root", e.raw_os_error().unwrap_or(0))
+    })?;
+
+    for path in [src, dest] {
+        let normalized = normalize_path(path);
+        if !normalized.starts_with(&canonical_root) {
+            return Err(CloneError::PathEscapesSandbox {
+                path: path.display().to_string(),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_normalize_path_collapses_parent_dir() {
+        let normalized = normalize_path(Path::new("/a/b/../c"));
+        assert_eq!(normalized, Path::new("/a/c"));
+    }
+
+    #[test]
+    fn test_normalize_path_skips_cur_dir() {
+        let normalized = normalize_path(Path::new("/a/./b"));
+        assert_eq!(normalized, Path::new("/a/b"));
+    }
+
+    #[test]
+    fn test_normalize_path_leading_parent_dir_is_kept() {
+        let normalized = normalize_path(Path::new("../a"));
+        assert_eq!(normalized, Path::new("../a"));
+    }
+
+    #[test]
+    fn test_normalize_path_does_not_require_existence() {
+        let normalized = normalize_path(Path::new("/tmp/does/not/exist/../exist"));
+        assert_eq!(normalized, Path::new("/tmp/does/not/exist"));
+    }
+
+    #[test]
+    fn test_ensure_in_sandbox_allows_paths_inside() {
+        let temp_dir = TempDir::new().unwrap();
+        // src/dest are only normalized lexically while the root is
+        // canonicalized, so build them from the canonical root (the temp
+        // dir may sit behind a symlink, e.g. under /var on macOS).
+        let sandbox = temp_dir.path().canonicalize().unwrap();
+        let src = sandbox.join("src.txt");
+        let dest = sandbox.join("sub/dest.txt");
+
+        assert!(ensure_in_sandbox(&src, &dest, &sandbox).is_ok());
+    }
+
+    #[test]
+    fn test_ensure_in_sandbox_rejects_parent_dir_escape() {
+        let temp_dir = TempDir::new().unwrap();
+        let sandbox = temp_dir.path().join("sandbox");
+        fs::create_dir(&sandbox).unwrap();
+        let src = sandbox.join("src.txt");
+        let dest = sandbox.join("../escaped.txt");
+
+        let result = ensure_in_sandbox(&src, &dest, &sandbox);
+        assert!(matches!(
+            result,
+            Err(CloneError::PathEscapesSandbox { .. })
+        ));
+    }
+
+    #[test]
+    fn test_ensure_in_sandbox_rejects_absolute_path_outside() {
+        let temp_dir = TempDir::new().unwrap();
+        let sandbox = temp_dir.path().join("sandbox");
+        fs::create_dir(&sandbox).unwrap();
+        let src = sandbox.join("src.txt");
+        let dest = PathBuf::from("/etc/passwd");
+
+        let result = ensure_in_sandbox(&src, &dest, &sandbox);
+        assert!(matches!(
+            result,
+            Err(CloneError::PathEscapesSandbox { .. })
+        ));
+    }
+}
@@ -0,0 +1,454 @@
+//! Cross-platform recursive tree cloning with per-path reporting.
+//!
+//! `clone_file`'s own directory handling is OS-native (`clonefile()` recurses
+//! on its own on macOS; Linux walks the tree internally and FICLONEs each
+//! regular file) and is all-or-nothing: one failing entry fails the whole
+//! call. `clone_tree` instead walks the source itself, delegating each
+//! regular file to `platform::clone_file`, so the same walk runs identically
+//! on both platforms and every visited path gets its own result.
+//!
+//! The walk is a plain recursive descent over `fs::read_dir`'s iterator (as
+//! `linux::clone_directory` already does), so it streams entries as it goes
+//! rather than collecting the whole tree into memory up front.
+//!
+//! # Security
+//! Regular files still go through `platform::clone_file`'s own file-type and
+//! (on Linux) TOCTOU-safe validation. Symlinks found during the walk are
+//! rejected (`skip_special_files: false`) or skipped-and-reported
+//! (`skip_special_files: true`) like any other special file unless
+//! `recreate_symlinks` is set, in which case they're recreated as symlinks
+//! — never followed — via `fs::read_link`.
+
+use crate::error::{CloneError, CloneResult};
+use crate::outcome::CloneMethod;
+use crate::platform;
+use napi_derive::napi;
+use std::fs;
+use std::os::unix::fs::{symlink, PermissionsExt};
+use std::path::Path;
+
+/// Outcome of cloning a single path within a `clone_tree` call.
+///
+/// At most one of `method`/`skipped`/`error` is set: `method` is set for a
+/// cloned regular file; none of the three is set for a created directory or
+/// recreated symlink (neither of which has a `CloneMethod`); `skipped` is
+/// `true` for a special file (or symlink, when `recreate_symlinks` is
+/// `false`) left out of `dest` instead of aborting; `error` is set when the
+/// path failed outright.
+#[napi(object)]
+pub struct CloneTreeEntryResult {
+    pub src: String,
+    pub dest: String,
+    pub method: Option<CloneMethod>,
+    pub skipped: bool,
+    pub error: Option<String>,
+}
+
+impl CloneTreeEntryResult {
+    fn ok(src: &Path, dest: &Path, method: Option<CloneMethod>) -> Self {
+        Self {
+            src: src.display().to_string(),
+            dest: dest.display().to_string(),
+            method,
+            skipped: false,
+            error: None,
+        }
+    }
+
+    fn skipped(src: &Path, dest: &Path) -> Self {
+        Self {
+            src: src.display().to_string(),
+            dest: dest.display().to_string(),
+            method: None,
+            skipped: true,
+            error: None,
+        }
+    }
+
+    fn failed(src: &Path, dest: &Path, error: &CloneError) -> Self {
+        Self {
+            src: src.display().to_string(),
+            dest: dest.display().to_string(),
+            method: None,
+            skipped: false,
+            error: Some(error.to_string()),
+        }
+    }
+}
+
+/// Recreate the symlink at `src` as a symlink at `dest`, without following
+/// it (`fs::read_link` reads the link's target text, it doesn't open it).
+fn clone_symlink(src: &Path, dest: &Path) -> CloneResult<()> {
+    let target = fs::read_link(src)
+        .map_err(|e| CloneError::from_errno("readlink source", e.raw_os_error().unwrap_or(0)))?;
+    symlink(target, dest)
+        .map_err(|e| CloneError::from_errno("symlink dest", e.raw_os_error().unwrap_or(0)))
+}
+
+/// Recursively clone `src` into `dest`, recording one `CloneTreeEntryResult`
+/// per path visited (including `src`/`dest` themselves). Regular files are
+/// cloned via `platform::clone_file`; `recreate_symlinks` chooses whether a
+/// symlink found in the walk is recreated as a symlink or treated like any
+/// other special file (devices, sockets, FIFOs), which `skip_special_files`
+/// in turn chooses to skip-and-report versus abort.
+///
+/// `stop_on_error` chooses between the two failure modes callers can pick
+/// between: `true` aborts the whole clone on the first failing entry and
+/// removes the partially created `dest` tree so the caller never observes a
+/// half-written destination; `false` continues past failures, recording
+/// each one in its own result so the caller gets a complete picture of what
+/// did and didn't make it across.
+#[allow(clippy::too_many_arguments)]
+pub fn clone_tree(
+    src: &Path,
+    dest: &Path,
+    allow_copy_fallback: bool,
+    skip_special_files: bool,
+    recreate_symlinks: bool,
+    stop_on_error: bool,
+    preserve_times: bool,
+    preserve_owner: bool,
+    preserve_xattrs: bool,
+) -> Vec<CloneTreeEntryResult> {
+    // Only a `dest` this call actually created may ever be rolled back: if
+    // `dest` already existed, the top-level `fs::create_dir` in `walk_dir`
+    // fails with `EEXIST` and `stop_on_error` would otherwise turn that into
+    // a `remove_dir_all` of a tree we never touched.
+    let dest_created_by_this_call = !dest.exists();
+
+    let mut results = Vec::new();
+    let aborted = walk_dir(
+        src,
+        dest,
+        allow_copy_fallback,
+        skip_special_files,
+        recreate_symlinks,
+        stop_on_error,
+        preserve_times,
+        preserve_owner,
+        preserve_xattrs,
+        &mut results,
+    )
+    .is_err();
+
+    if aborted && dest_created_by_this_call {
+        let _ = fs::remove_dir_all(dest);
+    }
+
+    results
+}
+
+/// Record `result` and, when `stop_on_error` is set, turn it into an `Err`
+/// so the caller unwinds and rolls back instead of visiting the rest of the
+/// tree.
+fn report(
+    result: CloneTreeEntryResult,
+    error: Option<CloneError>,
+    stop_on_error: bool,
+    results: &mut Vec<CloneTreeEntryResult>,
+) -> CloneResult<()> {
+    results.push(result);
+    match error {
+        Some(e) if stop_on_error => Err(e),
+        _ => Ok(()),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn walk_dir(
+    src: &Path,
+    dest: &Path,
+    allow_copy_fallback: bool,
+    skip_special_files: bool,
+    recreate_symlinks: bool,
+    stop_on_error: bool,
+    preserve_times: bool,
+    preserve_owner: bool,
+    preserve_xattrs: bool,
+    results: &mut Vec<CloneTreeEntryResult>,
+) -> CloneResult<()> {
+    let src_mode = match fs::metadata(src) {
+        Ok(m) => m.permissions().mode(),
+        Err(e) => {
+            let error = CloneError::from_errno("stat source", e.raw_os_error().unwrap_or(0));
+            return report(
+                CloneTreeEntryResult::failed(src, dest, &error),
+                Some(error),
+                stop_on_error,
+                results,
+            );
+        }
+    };
+
+    if let Err(e) = fs::create_dir(dest) {
+        let error = CloneError::from_errno("mkdir dest", e.raw_os_error().unwrap_or(0));
+        return report(
+            CloneTreeEntryResult::failed(src, dest, &error),
+            Some(error),
+            stop_on_error,
+            results,
+        );
+    }
+    if let Err(e) = fs::set_permissions(dest, fs::Permissions::from_mode(src_mode)) {
+        let error = CloneError::from_errno("chmod dest", e.raw_os_error().unwrap_or(0));
+        return report(
+            CloneTreeEntryResult::failed(src, dest, &error),
+            Some(error),
+            stop_on_error,
+            results,
+        );
+    }
+
+    let entries = match fs::read_dir(src) {
+        Ok(entries) => entries,
+        Err(e) => {
+            let error = CloneError::from_errno("readdir source", e.raw_os_error().unwrap_or(0));
+            return report(
+                CloneTreeEntryResult::failed(src, dest, &error),
+                Some(error),
+                stop_on_error,
+                results,
+            );
+        }
+    };
+    results.push(CloneTreeEntryResult::ok(src, dest, None));
+
+    for entry in entries {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                let error = CloneError::from_errno("readdir source", e.raw_os_error().unwrap_or(0));
+                report(
+                    CloneTreeEntryResult::failed(src, dest, &error),
+                    Some(error),
+                    stop_on_error,
+                    results,
+                )?;
+                continue;
+            }
+        };
+        let entry_src = entry.path();
+        let entry_dest = dest.join(entry.file_name());
+
+        let file_type = match entry.file_type() {
+            Ok(file_type) => file_type,
+            Err(e) => {
+                let error = CloneError::from_errno("stat", e.raw_os_error().unwrap_or(0));
+                report(
+                    CloneTreeEntryResult::failed(&entry_src, &entry_dest, &error),
+                    Some(error),
+                    stop_on_error,
+                    results,
+                )?;
+                continue;
+            }
+        };
+
+        if file_type.is_dir() {
+            walk_dir(
+                &entry_src,
+                &entry_dest,
+                allow_copy_fallback,
+                skip_special_files,
+                recreate_symlinks,
+                stop_on_error,
+                preserve_times,
+                preserve_owner,
+                preserve_xattrs,
+                results,
+            )?;
+        } else if file_type.is_file() {
+            match platform::clone_file(
+                &entry_src,
+                &entry_dest,
+                allow_copy_fallback,
+                skip_special_files,
+                true,
+                true,
+                preserve_times,
+                preserve_owner,
+                preserve_xattrs,
+            ) {
+                Ok(method) => results.push(CloneTreeEntryResult::ok(
+                    &entry_src,
+                    &entry_dest,
+                    Some(method),
+                )),
+                Err(e) => report(
+                    CloneTreeEntryResult::failed(&entry_src, &entry_dest, &e),
+                    Some(e),
+                    stop_on_error,
+                    results,
+                )?,
+            }
+        } else if file_type.is_symlink() && recreate_symlinks {
+            match clone_symlink(&entry_src, &entry_dest) {
+                Ok(()) => results.push(CloneTreeEntryResult::ok(&entry_src, &entry_dest, None)),
+                Err(e) => report(
+                    CloneTreeEntryResult::failed(&entry_src, &entry_dest, &e),
+                    Some(e),
+                    stop_on_error,
+                    results,
+                )?,
+            }
+        } else if skip_special_files {
+            results.push(CloneTreeEntryResult::skipped(&entry_src, &entry_dest));
+        } else {
+            let error = CloneError::UnsupportedFileType {
+                file_type: special_file_type_name(&file_type),
+            };
+            report(
+                CloneTreeEntryResult::failed(&entry_src, &entry_dest, &error),
+                Some(error),
+                stop_on_error,
+                results,
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Name a non-regular, non-directory, non-(recreated-)symlink file type for
+/// error messages.
+fn special_file_type_name(file_type: &fs::FileType) -> &'static str {
+    use std::os::unix::fs::FileTypeExt;
+
+    if file_type.is_symlink() {
+        "symlink"
+    } else if file_type.is_block_device() {
+        "block device"
+    } else if file_type.is_char_device() {
+        "character device"
+    } else if file_type.is_fifo() {
+        "FIFO (named pipe)"
+    } else if file_type.is_socket() {
+        "socket"
+    } else {
+        "unknown"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::symlink as make_symlink;
+    use tempfile::TempDir;
+
+    fn entry<'a>(
+        results: &'a [CloneTreeEntryResult],
+        dest_suffix: &str,
+    ) -> &'a CloneTreeEntryResult {
+        results
+            .iter()
+            .find(|r| r.dest.ends_with(dest_suffix))
+            .unwrap_or_else(|| panic!("no result for {dest_suffix}"))
+    }
+
+    #[test]
+    fn test_clone_tree_clones_nested_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let src = temp_dir.path().join("src");
+        let dest = temp_dir.path().join("dest");
+        fs::create_dir_all(src.join("sub")).unwrap();
+        fs::write(src.join("a.txt"), "a").unwrap();
+        fs::write(src.join("sub/b.txt"), "b").unwrap();
+
+        let results = clone_tree(&src, &dest, true, false, false, false, false, false, false);
+
+        assert!(results.iter().all(|r| r.error.is_none()));
+        assert_eq!(fs::read_to_string(dest.join("a.txt")).unwrap(), "a");
+        assert_eq!(fs::read_to_string(dest.join("sub/b.txt")).unwrap(), "b");
+        assert!(entry(&results, "a.txt").method.is_some());
+    }
+
+    #[test]
+    fn test_clone_tree_recreates_symlinks_when_requested() {
+        let temp_dir = TempDir::new().unwrap();
+        let src = temp_dir.path().join("src");
+        let dest = temp_dir.path().join("dest");
+        fs::create_dir(&src).unwrap();
+        fs::write(src.join("target.txt"), "content").unwrap();
+        make_symlink("target.txt", src.join("link.txt")).unwrap();
+
+        let results = clone_tree(&src, &dest, true, false, true, false, false, false, false);
+
+        assert!(results.iter().all(|r| r.error.is_none()));
+        let cloned_link = fs::symlink_metadata(dest.join("link.txt")).unwrap();
+        assert!(cloned_link.file_type().is_symlink());
+    }
+
+    #[test]
+    fn test_clone_tree_skips_and_reports_special_files_when_requested() {
+        let temp_dir = TempDir::new().unwrap();
+        let src = temp_dir.path().join("src");
+        let dest = temp_dir.path().join("dest");
+        fs::create_dir(&src).unwrap();
+        fs::write(src.join("keep.txt"), "keep").unwrap();
+        make_symlink("keep.txt", src.join("link.txt")).unwrap();
+
+        let results = clone_tree(&src, &dest, true, true, false, false, false, false, false);
+
+        assert!(!dest.join("link.txt").exists());
+        assert!(entry(&results, "link.txt").skipped);
+        assert!(dest.join("keep.txt").exists());
+    }
+
+    #[test]
+    fn test_clone_tree_rejects_special_files_without_skip() {
+        let temp_dir = TempDir::new().unwrap();
+        let src = temp_dir.path().join("src");
+        let dest = temp_dir.path().join("dest");
+        fs::create_dir(&src).unwrap();
+        make_symlink("nowhere", src.join("link.txt")).unwrap();
+
+        let results = clone_tree(&src, &dest, true, false, false, false, false, false, false);
+
+        assert!(entry(&results, "link.txt").error.is_some());
+    }
+
+    #[test]
+    fn test_clone_tree_stop_on_error_rolls_back_dest() {
+        let temp_dir = TempDir::new().unwrap();
+        let src = temp_dir.path().join("src");
+        let dest = temp_dir.path().join("dest");
+        fs::create_dir(&src).unwrap();
+        fs::write(src.join("a.txt"), "a").unwrap();
+        make_symlink("nowhere", src.join("b_link.txt")).unwrap();
+
+        clone_tree(&src, &dest, true, false, false, true, false, false, false);
+
+        assert!(!dest.exists());
+    }
+
+    #[test]
+    fn test_clone_tree_stop_on_error_does_not_delete_preexisting_dest() {
+        let temp_dir = TempDir::new().unwrap();
+        let src = temp_dir.path().join("src");
+        let dest = temp_dir.path().join("dest");
+        fs::create_dir(&src).unwrap();
+        fs::write(src.join("a.txt"), "a").unwrap();
+        fs::create_dir(&dest).unwrap();
+        fs::write(dest.join("keep.txt"), "keep").unwrap();
+
+        clone_tree(&src, &dest, true, false, false, true, false, false, false);
+
+        assert!(dest.exists());
+        assert!(dest.join("keep.txt").exists());
+    }
+
+    #[test]
+    fn test_clone_tree_without_stop_on_error_keeps_partial_dest() {
+        let temp_dir = TempDir::new().unwrap();
+        let src = temp_dir.path().join("src");
+        let dest = temp_dir.path().join("dest");
+        fs::create_dir(&src).unwrap();
+        fs::write(src.join("a.txt"), "a").unwrap();
+        make_symlink("nowhere", src.join("b_link.txt")).unwrap();
+
+        let results = clone_tree(&src, &dest, true, false, false, false, false, false, false);
+
+        assert!(dest.join("a.txt").exists());
+        assert!(entry(&results, "b_link.txt").error.is_some());
+    }
+}